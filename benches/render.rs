@@ -0,0 +1,40 @@
+//! Cost of turning the display into an image each frame: `cargo bench --bench render`
+#[path = "../src/render.rs"]
+mod render;
+
+use std::hint::black_box;
+
+use bevy::color::LinearRgba;
+use chip8_core::{
+	Framebuffer,
+	chip8::{DISPLAY_HEIGHT, DISPLAY_HEIGHT_HIGHRES, DISPLAY_WIDTH, DISPLAY_WIDTH_HIGHRES},
+};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+use crate::render::render_image;
+
+/// Alternating pixels, so both colors are used everywhere
+const CHECKERBOARD: u128 = 0xAAAA_AAAA_AAAA_AAAA_AAAA_AAAA_AAAA_AAAA;
+
+fn bench(c: &mut Criterion)
+{
+	let mut group = c.benchmark_group("render_image");
+	for (name, width, height) in [
+		("low res", DISPLAY_WIDTH, DISPLAY_HEIGHT),
+		("high res", DISPLAY_WIDTH_HIGHRES, DISPLAY_HEIGHT_HIGHRES),
+	]
+	{
+		let mut display = Framebuffer::new(width, height, 1);
+		for y in 0..DISPLAY_HEIGHT_HIGHRES
+		{
+			display.set_row(0, y, if y % 2 == 0 { CHECKERBOARD } else { !CHECKERBOARD });
+		}
+		group.bench_function(name, |b| {
+			b.iter(|| render_image(black_box(&display), LinearRgba::BLACK, LinearRgba::WHITE))
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Compares `BlockCache` against the interpreter: `cargo bench -p chip8-core --bench cache`
+use std::hint::black_box;
+
+use chip8_core::{BlockCache, Chip8};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// Nested counting loops with a subroutine call and some flag arithmetic, never drawing so frames don't stall
+#[rustfmt::skip]
+const ARITHMETIC: [u8; 24] = [
+	0x60, 0x00, 0x61, 0x00, 0x71, 0x01, 0x82, 0x14, 0x83, 0x25, 0x22, 0x14, 0x31, 0x00, 0x12, 0x04,
+	0x70, 0x01, 0x12, 0x02, 0x84, 0x36, 0x00, 0xEE,
+];
+const TICKS: usize = 1000;
+const FRAMES: usize = 60;
+
+fn machine() -> Chip8
+{
+	let mut cpu = Chip8::new();
+	cpu.load_code(ARITHMETIC);
+	cpu
+}
+
+fn bench(c: &mut Criterion)
+{
+	let mut group = c.benchmark_group("second of emulation at 1000 instructions per frame");
+	group.bench_function("interpreter", |b| {
+		b.iter(|| {
+			let mut cpu = machine();
+			for _ in 0..FRAMES
+			{
+				cpu.run_frame(black_box(TICKS));
+			}
+			cpu.registers
+		})
+	});
+	group.bench_function("block cache", |b| {
+		b.iter(|| {
+			let mut cpu = machine();
+			let mut cache = BlockCache::new();
+			for _ in 0..FRAMES
+			{
+				cache.run_frame(&mut cpu, black_box(TICKS));
+			}
+			cpu.registers
+		})
+	});
+	group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+//! Interpreter throughput on a few typical workloads: `cargo bench -p chip8-core --bench interpreter`
+use std::hint::black_box;
+
+use chip8_core::{Chip8, Platform, Quirks};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// Nested counting loops with a subroutine call and some flag arithmetic
+#[rustfmt::skip]
+const ARITHMETIC: &[u8] = &[
+	0x60, 0x00, 0x61, 0x00, 0x71, 0x01, 0x82, 0x14, 0x83, 0x25, 0x22, 0x14, 0x31, 0x00, 0x12, 0x04,
+	0x70, 0x01, 0x12, 0x02, 0x84, 0x36, 0x00, 0xEE,
+];
+/// Draws digits all over the screen, checking for collisions
+#[rustfmt::skip]
+const DRAW: &[u8] = &[
+	0x00, 0xE0, 0xF2, 0x29, 0xD0, 0x15, 0x3F, 0x00, 0x72, 0x01, 0x70, 0x05, 0x71, 0x03, 0x12, 0x02,
+];
+/// Switches to high resolution and scrolls the screen every way while drawing into it
+#[rustfmt::skip]
+const SCROLL_HIGH_RES: &[u8] = &[
+	0x00, 0xFF, 0xA0, 0x00, 0xD0, 0x15, 0x00, 0xC3, 0x00, 0xFB, 0x00, 0xFC, 0x00, 0xFB, 0x70, 0x07,
+	0x71, 0x05, 0x12, 0x04,
+];
+
+const FRAMES: usize = 60;
+const TICKS: usize = 1000;
+
+/// Display wait is off so drawing workloads run all their instructions instead of stopping at the first DXYN
+fn machine(code: &[u8]) -> Chip8
+{
+	let mut cpu = Chip8::new();
+	cpu.quirks = Quirks {
+		display_wait: false,
+		..Quirks::for_platform(Platform::SuperChip)
+	};
+	cpu.load_code(code);
+	cpu
+}
+
+fn bench(c: &mut Criterion)
+{
+	for (name, code) in [
+		("arithmetic", ARITHMETIC),
+		("draw", DRAW),
+		("scroll high res", SCROLL_HIGH_RES),
+	]
+	{
+		let mut group = c.benchmark_group(name);
+		// `run` keeps the wall clock timers
+		group.bench_function("run", |b| {
+			b.iter(|| {
+				let mut cpu = machine(code);
+				cpu.run(black_box(FRAMES * TICKS));
+				cpu.display
+			})
+		});
+		group.bench_function("run_frame", |b| {
+			b.iter(|| {
+				let mut cpu = machine(code);
+				for _ in 0..FRAMES
+				{
+					cpu.run_frame(black_box(TICKS));
+				}
+				cpu.display
+			})
+		});
+		group.finish();
+	}
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+use chip8_core::{BlockCache, Chip8};
+
+/// Enough for loops and subroutines to get going without slowing the fuzzer down
+const FRAMES: usize = 20;
+const TICKS_PER_FRAME: usize = 100;
+
+/// Runs the same machine on the interpreter and through a block cache, which must never disagree
+pub fn run_both(mut interpreted: Chip8, mut cached: Chip8)
+{
+	let mut cache = BlockCache::new();
+	let (mut expected, mut actual) = (vec![0; interpreted.state_size()], vec![0; cached.state_size()]);
+	for frame in 0..FRAMES
+	{
+		interpreted.run_frame(TICKS_PER_FRAME);
+		cache.run_frame(&mut cached, TICKS_PER_FRAME);
+		interpreted.save_state(&mut expected).unwrap();
+		cached.save_state(&mut actual).unwrap();
+		assert!(expected == actual, "block cache diverged on frame {}", frame);
+	}
+}
@@ -0,0 +1,66 @@
+//! Runs from an arbitrary machine state, including stack pointers and addresses `load_state` would reject
+#![no_main]
+
+mod common;
+
+use arbitrary::Arbitrary;
+use chip8_core::{Chip8, MemoryProfile};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Machine
+{
+	program_counter: u16,
+	stack_pointer: u8,
+	registers: [u8; 16],
+	reg_i: u16,
+	stack: [u16; 16],
+	keys: [bool; 16],
+	reg_dt: u8,
+	reg_st: u8,
+	high_res: bool,
+	shift_vx: bool,
+	jump_vx: bool,
+	logic_reset_vf: bool,
+	memory_increment_i: bool,
+	wrap_sprites: bool,
+	display_wait: bool,
+	/// Index into `MemoryProfile::PRESETS`
+	memory: u8,
+	/// Written to RAM starting at the program counter
+	code: Vec<u8>,
+}
+
+impl Machine
+{
+	fn build(&self) -> Chip8
+	{
+		let presets = MemoryProfile::PRESETS;
+		let mut cpu = Chip8::with_memory(presets[self.memory as usize % presets.len()].1);
+		let ram_size = cpu.memory().ram_size;
+		cpu.program_counter = self.program_counter as usize;
+		cpu.stack_pointer = self.stack_pointer as usize;
+		cpu.registers = self.registers;
+		cpu.reg_i = self.reg_i;
+		cpu.stack = self.stack;
+		cpu.keys = self.keys;
+		cpu.reg_dt = self.reg_dt;
+		cpu.reg_st = self.reg_st;
+		cpu.set_high_res(self.high_res);
+		cpu.quirks.shift_vx = self.shift_vx;
+		cpu.quirks.jump_vx = self.jump_vx;
+		cpu.quirks.logic_reset_vf = self.logic_reset_vf;
+		cpu.quirks.memory_increment_i = self.memory_increment_i;
+		cpu.quirks.wrap_sprites = self.wrap_sprites;
+		cpu.quirks.display_wait = self.display_wait;
+		for (offset, byte) in self.code.iter().take(ram_size).enumerate()
+		{
+			cpu.ram[(cpu.program_counter + offset) % ram_size] = *byte;
+		}
+		cpu
+	}
+}
+
+fuzz_target!(|machine: Machine| {
+	common::run_both(machine.build(), machine.build());
+});
@@ -0,0 +1,27 @@
+//! Runs arbitrary bytes as a ROM. The first byte picks the platform, the next two hold the keypad state and the rest
+//! is loaded at 0x200.
+#![no_main]
+
+mod common;
+
+use chip8_core::{Chip8, Platform, Quirks, chip8::MEMORY_CAPACITY};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let [platform, keys_low, keys_high, rom @ ..] = data
+	else
+	{
+		return;
+	};
+	let platform = Platform::ALL[*platform as usize % Platform::ALL.len()];
+	let keys = u16::from_le_bytes([*keys_low, *keys_high]);
+	let rom = &rom[..rom.len().min(MEMORY_CAPACITY - 0x200)];
+	let machine = || {
+		let mut cpu = Chip8::new();
+		cpu.quirks = Quirks::for_platform(platform);
+		cpu.keys = core::array::from_fn(|key| keys & (1 << key) != 0);
+		cpu.load_code(rom);
+		cpu
+	};
+	common::run_both(machine(), machine());
+});
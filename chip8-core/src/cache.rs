@@ -0,0 +1,315 @@
+use crate::{chip8::Chip8, memory::MAX_MEMORY};
+
+/// An instruction decoded once into the fields it uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op
+{
+	/// Not decoded yet, or overwritten since
+	Undecoded,
+	Clear,
+	Return,
+	Jump(u16),
+	Call(u16),
+	SkipEq(u8, u8),
+	SkipNe(u8, u8),
+	SkipEqReg(u8, u8),
+	SkipNeReg(u8, u8),
+	Set(u8, u8),
+	Add(u8, u8),
+	Move(u8, u8),
+	Or(u8, u8),
+	And(u8, u8),
+	Xor(u8, u8),
+	AddReg(u8, u8),
+	Sub(u8, u8),
+	ShiftRight(u8, u8),
+	SubN(u8, u8),
+	ShiftLeft(u8, u8),
+	SetI(u16),
+	SkipKey(u8),
+	SkipNotKey(u8),
+	ReadDelay(u8),
+	SetDelay(u8),
+	SetSound(u8),
+	AddI(u8),
+	Font(u8),
+	Load(u8),
+	/// Left to the interpreter: drawing, randomness, key waits, memory writes and anything unusual
+	Interpret(u16),
+}
+
+impl Op
+{
+	fn decode(instruction: u16) -> Self
+	{
+		let x = ((instruction & 0x0F00) >> 8) as u8;
+		let y = ((instruction & 0x00F0) >> 4) as u8;
+		let nn = (instruction & 0x00FF) as u8;
+		let nnn = instruction & 0x0FFF;
+		match (instruction >> 12, instruction & 0xF)
+		{
+			(0x0, _) if instruction == 0x00E0 => Op::Clear,
+			(0x0, _) if instruction == 0x00EE => Op::Return,
+			(0x1, _) => Op::Jump(nnn),
+			(0x2, _) => Op::Call(nnn),
+			(0x3, _) => Op::SkipEq(x, nn),
+			(0x4, _) => Op::SkipNe(x, nn),
+			(0x5, _) => Op::SkipEqReg(x, y),
+			(0x6, _) => Op::Set(x, nn),
+			(0x7, _) => Op::Add(x, nn),
+			(0x8, 0x0) => Op::Move(x, y),
+			(0x8, 0x1) => Op::Or(x, y),
+			(0x8, 0x2) => Op::And(x, y),
+			(0x8, 0x3) => Op::Xor(x, y),
+			(0x8, 0x4) => Op::AddReg(x, y),
+			(0x8, 0x5) => Op::Sub(x, y),
+			(0x8, 0x6) => Op::ShiftRight(x, y),
+			(0x8, 0x7) => Op::SubN(x, y),
+			(0x8, 0xE) => Op::ShiftLeft(x, y),
+			(0x9, _) => Op::SkipNeReg(x, y),
+			(0xA, _) => Op::SetI(nnn),
+			(0xE, _) if nn == 0x9E => Op::SkipKey(x),
+			(0xE, _) if nn == 0xA1 => Op::SkipNotKey(x),
+			(0xF, _) => match nn
+			{
+				0x07 => Op::ReadDelay(x),
+				0x15 => Op::SetDelay(x),
+				0x18 => Op::SetSound(x),
+				0x1E => Op::AddI(x),
+				0x29 => Op::Font(x),
+				0x65 => Op::Load(x),
+				_ => Op::Interpret(instruction),
+			},
+			_ => Op::Interpret(instruction),
+		}
+	}
+
+	/// Whether execution can continue straight on to the next instruction
+	fn falls_through(&self) -> bool
+	{
+		!matches!(
+			self,
+			Op::Return
+				| Op::Jump(_)
+				| Op::Call(_)
+				| Op::SkipEq(..)
+				| Op::SkipNe(..)
+				| Op::SkipEqReg(..)
+				| Op::SkipNeReg(..)
+				| Op::SkipKey(_)
+				| Op::SkipNotKey(_)
+				| Op::Interpret(_)
+		)
+	}
+}
+
+/// A faster way to run a `Chip8` for batch work, producing exactly the same results as `Chip8::run_frame`.
+/// Each basic block is decoded once, on first use, and kept until the memory holding it is written to. Writes made by
+/// FX33 and FX55 are tracked, anything else writing to `ram` has to call `invalidate`.
+pub struct BlockCache
+{
+	/// The decoded instruction starting at each address
+	ops: [Op; MAX_MEMORY],
+	/// One past the highest address decoded, so invalidating only clears what the profile's RAM could have filled
+	decoded_end: usize,
+}
+
+impl Default for BlockCache
+{
+	fn default() -> Self
+	{
+		Self::new()
+	}
+}
+
+impl BlockCache
+{
+	pub const fn new() -> Self
+	{
+		Self {
+			ops: [Op::Undecoded; MAX_MEMORY],
+			decoded_end: 0,
+		}
+	}
+
+	/// Forgets everything decoded, needed after loading a ROM or state or editing `ram` directly
+	pub fn invalidate(&mut self)
+	{
+		self.ops[..self.decoded_end].fill(Op::Undecoded);
+		self.decoded_end = 0;
+	}
+
+	/// Forgets instructions overlapping `len` bytes written at `start`
+	pub fn invalidate_range(&mut self, start: usize, len: usize)
+	{
+		let end = (start + len).min(MAX_MEMORY);
+		// The instruction starting one byte earlier covers the first byte too
+		for op in &mut self.ops[start.saturating_sub(1).min(end)..end]
+		{
+			*op = Op::Undecoded;
+		}
+	}
+
+	/// Same as `Chip8::run_frame`: up to `ticks` instructions followed by one timer update
+	pub fn run_frame(&mut self, cpu: &mut Chip8, ticks: usize)
+	{
+		for _ in 0..ticks
+		{
+			if cpu.is_halted || cpu.wait_for_vblank
+			{
+				break;
+			}
+			let pc = cpu.program_counter;
+			if pc >= cpu.memory().ram_size
+			{
+				cpu.is_halted = true;
+				break;
+			}
+			if self.ops[pc] == Op::Undecoded
+			{
+				self.decode_block(cpu, pc);
+			}
+			self.execute(cpu, self.ops[pc]);
+		}
+		cpu.process_timers();
+		cpu.vblank();
+	}
+
+	/// Same as `Chip8::step`, forgetting any code the instruction writes over
+	pub fn step(&mut self, cpu: &mut Chip8)
+	{
+		match cpu.instruction_at(cpu.program_counter)
+		{
+			Some(instruction) => self.interpret(cpu, instruction),
+			None => cpu.step(),
+		}
+	}
+
+	/// Decodes from `start` up to the end of the basic block, or an instruction that is already decoded
+	fn decode_block(&mut self, cpu: &Chip8, start: usize)
+	{
+		let mut addr = start;
+		while addr < cpu.memory().ram_size && self.ops[addr] == Op::Undecoded
+		{
+			// The last byte of RAM is left for the interpreter to fail on, just as it would when run directly
+			let op = cpu.instruction_at(addr).map_or(Op::Interpret(0), Op::decode);
+			self.ops[addr] = op;
+			self.decoded_end = self.decoded_end.max(addr + 1);
+			if !op.falls_through()
+			{
+				break;
+			}
+			addr += 2;
+		}
+	}
+
+	fn execute(&mut self, cpu: &mut Chip8, op: Op)
+	{
+		let v = &mut cpu.registers;
+		let mut skip = false;
+		match op
+		{
+			Op::Undecoded => unreachable!("instructions are decoded before they run"),
+			Op::Clear =>
+			{
+				cpu.need_draw = true;
+				cpu.display.clear();
+			}
+			Op::Return => cpu.ret(),
+			Op::Jump(addr) => cpu.jump(addr),
+			Op::Call(addr) => cpu.call(addr),
+			Op::SkipEq(x, nn) => skip = v[x as usize] == nn,
+			Op::SkipNe(x, nn) => skip = v[x as usize] != nn,
+			Op::SkipEqReg(x, y) => skip = v[x as usize] == v[y as usize],
+			Op::SkipNeReg(x, y) => skip = v[x as usize] != v[y as usize],
+			Op::Set(x, nn) => v[x as usize] = nn,
+			Op::Add(x, nn) => v[x as usize] = v[x as usize].wrapping_add(nn),
+			Op::Move(x, y) => v[x as usize] = v[y as usize],
+			Op::Or(x, y) => logic(v, x, y, cpu.quirks.logic_reset_vf, |a, b| a | b),
+			Op::And(x, y) => logic(v, x, y, cpu.quirks.logic_reset_vf, |a, b| a & b),
+			Op::Xor(x, y) => logic(v, x, y, cpu.quirks.logic_reset_vf, |a, b| a ^ b),
+			Op::AddReg(x, y) =>
+			{
+				let r = v[x as usize] as u16 + v[y as usize] as u16;
+				v[x as usize] = r as u8;
+				v[0xF] = (r > 255) as u8;
+			}
+			Op::Sub(x, y) =>
+			{
+				let (vx, vy) = (v[x as usize], v[y as usize]);
+				v[x as usize] = vx.wrapping_sub(vy);
+				v[0xF] = (vx >= vy) as u8;
+			}
+			Op::SubN(x, y) =>
+			{
+				let (vx, vy) = (v[x as usize], v[y as usize]);
+				v[x as usize] = vy.wrapping_sub(vx);
+				v[0xF] = (vx <= vy) as u8;
+			}
+			Op::ShiftRight(x, y) =>
+			{
+				let src = v[if cpu.quirks.shift_vx { x } else { y } as usize];
+				v[x as usize] = src >> 1;
+				v[0xF] = src & 0x1;
+			}
+			Op::ShiftLeft(x, y) =>
+			{
+				let src = v[if cpu.quirks.shift_vx { x } else { y } as usize];
+				v[x as usize] = src << 1;
+				v[0xF] = src >> 7;
+			}
+			Op::SetI(addr) => cpu.reg_i = addr,
+			Op::SkipKey(x) => skip = cpu.keys[(v[x as usize] & 0xF) as usize],
+			Op::SkipNotKey(x) => skip = !cpu.keys[(v[x as usize] & 0xF) as usize],
+			Op::ReadDelay(x) => v[x as usize] = cpu.reg_dt,
+			Op::SetDelay(x) => cpu.reg_dt = v[x as usize],
+			Op::SetSound(x) => cpu.reg_st = v[x as usize],
+			Op::AddI(x) => cpu.reg_i = cpu.reg_i.wrapping_add(v[x as usize] as u16),
+			Op::Font(x) =>
+			{
+				let value = v[x as usize];
+				cpu.reg_i = cpu.digit_address(value);
+			}
+			Op::Load(x) => cpu.load_registers(x as usize + 1),
+			Op::Interpret(instruction) =>
+			{
+				// `step` moves past the instruction itself
+				self.interpret(cpu, instruction);
+				return;
+			}
+		}
+		cpu.program_counter = cpu.program_counter.wrapping_add(if skip { 4 } else { 2 });
+	}
+
+	/// Runs `instruction`, the one at the program counter, on the interpreter
+	fn interpret(&mut self, cpu: &mut Chip8, instruction: u16)
+	{
+		let i = cpu.reg_i as usize;
+		cpu.step();
+		match instruction & 0xF0FF
+		{
+			0xF033 => self.invalidate_wrapping(cpu, i, 3),
+			0xF055 => self.invalidate_wrapping(cpu, i, ((instruction & 0x0F00) >> 8) as usize + 1),
+			_ => (),
+		}
+	}
+
+	/// `invalidate_range` for writes through I, which wrap around the end of RAM
+	fn invalidate_wrapping(&mut self, cpu: &Chip8, start: usize, len: usize)
+	{
+		for offset in 0..len
+		{
+			self.invalidate_range((start + offset) % cpu.memory().ram_size, 1);
+		}
+	}
+}
+
+/// 8XY1-8XY3, which clear VF on the original interpreter
+fn logic(v: &mut [u8; 16], x: u8, y: u8, reset_vf: bool, op: fn(u8, u8) -> u8)
+{
+	v[x as usize] = op(v[x as usize], v[y as usize]);
+	if reset_vf
+	{
+		v[0xF] = 0;
+	}
+}
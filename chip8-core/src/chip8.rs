@@ -1,8 +1,14 @@
+#[cfg(feature = "std")]
 use std::time::SystemTime;
 
-use bevy::math::bool;
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg32;
+
+use crate::{
+	framebuffer::{Framebuffer, MAX_WIDTH},
+	memory::{MAX_MEMORY, MemoryProfile},
+	quirks::Quirks,
+};
 #[cfg(feature = "tracing")]
 use tracing::info_span;
 
@@ -13,28 +19,37 @@ pub const DISPLAY_HEIGHT: usize = DISPLAY_SIZE;
 pub const DISPLAY_WIDTH_HIGHRES: usize = DISPLAY_WIDTH * 2;
 pub const DISPLAY_HEIGHT_HIGHRES: usize = DISPLAY_HEIGHT * 2;
 
+#[derive(Clone)]
 pub struct Chip8
 {
 	pub program_counter: usize,
 	pub stack_pointer: usize,
 	pub registers: [u8; 16],
 	pub reg_i: u16,
-	pub ram: [u8; MEMORY_CAPACITY],
+	/// Room for the largest profile the build supports, only the first `memory().ram_size` bytes are used
+	pub ram: [u8; MAX_MEMORY],
 	pub stack: [u16; 16],
 
 	pub keys: [bool; 16],
-	pub display: [u128; 64],
+	/// 64x32 at low resolution and 128x64 at high resolution
+	pub display: Framebuffer,
 
 	pub reg_st: u8,
 	pub reg_dt: u8,
 	pub is_halted: bool,
-	pub high_res: bool,
 
 	pub need_draw: bool,
+	pub quirks: Quirks,
+	memory: MemoryProfile,
 
-	wait_for_vblank: bool,
+	pub(crate) wait_for_vblank: bool,
+	/// Wall clock time of the last timer update, only used by `tick`
+	#[cfg(feature = "std")]
 	timer: SystemTime,
 	rng: Pcg32,
+	/// Seed and number of values drawn since, enough to restore the generator from a save state
+	pub(crate) rng_seed: u64,
+	pub(crate) rng_draws: u64,
 }
 
 impl Default for Chip8
@@ -46,17 +61,21 @@ impl Default for Chip8
 			stack_pointer: Default::default(),
 			registers: Default::default(),
 			reg_i: Default::default(),
-			ram: [0; MEMORY_CAPACITY],
+			ram: [0; MAX_MEMORY],
 			stack: Default::default(),
-			display: [0; DISPLAY_HEIGHT_HIGHRES],
+			display: Framebuffer::new(DISPLAY_WIDTH, DISPLAY_HEIGHT, 1),
 			reg_st: Default::default(),
 			reg_dt: Default::default(),
 			is_halted: Default::default(),
 			need_draw: false,
-			high_res: false,
+			quirks: Quirks::default(),
+			memory: MemoryProfile::default(),
 			keys: Default::default(),
+			#[cfg(feature = "std")]
 			timer: SystemTime::now(),
-			rng: Pcg32::seed_from_u64(5),
+			rng: Pcg32::seed_from_u64(DEFAULT_SEED),
+			rng_seed: DEFAULT_SEED,
+			rng_draws: 0,
 			wait_for_vblank: false,
 		}
 	}
@@ -80,90 +99,125 @@ pub const CHIP_DIGITS: [u8; 16 * 5] = [
 	0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
 	0xF0, 0x80, 0xF0, 0x80, 0x80, //F
 ];
-const MEMORY_CAPACITY: usize = 4096;
+/// RAM of the standard profile
+pub const MEMORY_CAPACITY: usize = MemoryProfile::STANDARD.ram_size;
+const DEFAULT_SEED: u64 = 5;
 
 impl Chip8
 {
 	pub fn new() -> Self
 	{
-		let mem = Self::init();
-		Chip8 {
-			ram: mem,
+		Self::with_memory(MemoryProfile::default())
+	}
+
+	/// A machine with the given RAM size and layout, panicking if it doesn't pass `MemoryProfile::validate`
+	pub fn with_memory(memory: MemoryProfile) -> Self
+	{
+		Self::try_with_memory(memory).unwrap_or_else(|err| panic!("invalid memory profile: {}", err))
+	}
+
+	/// A machine with the given RAM size and layout, or why the layout can't work
+	pub fn try_with_memory(memory: MemoryProfile) -> Result<Self, &'static str>
+	{
+		memory.validate()?;
+		let mut cpu = Chip8 {
+			memory,
 			..Default::default()
-		}
+		};
+		cpu.init();
+		Ok(cpu)
+	}
+
+	/// Puts RAM back as it is at power on: empty apart from the font
+	pub fn init(&mut self)
+	{
+		self.ram.fill(0);
+		let font = self.memory.font_address;
+		self.ram[font..font + CHIP_DIGITS.len()].copy_from_slice(&CHIP_DIGITS);
+	}
+
+	pub fn memory(&self) -> &MemoryProfile
+	{
+		&self.memory
 	}
 
-	pub fn init() -> [u8; MEMORY_CAPACITY]
+	/// Copies `code` to the profile's start address and jumps to it, panicking if it is larger than
+	/// `memory().rom_space()`
+	pub fn load_code(&mut self, code: impl AsRef<[u8]>) -> &mut Self
 	{
-		let mut mem = [0; MEMORY_CAPACITY];
-		for (i, mem_entry) in mem.iter_mut().enumerate()
+		self.try_load_code(code).unwrap_or_else(|err| panic!("{}", err))
+	}
+
+	/// Copies `code` to the profile's start address and jumps to it, leaving the machine untouched if it is larger
+	/// than `memory().rom_space()`
+	pub fn try_load_code(&mut self, code: impl AsRef<[u8]>) -> Result<&mut Self, &'static str>
+	{
+		let code = code.as_ref();
+		if code.len() > self.memory.rom_space()
 		{
-			if i < CHIP_DIGITS.len()
-			{
-				*mem_entry = CHIP_DIGITS[i];
-			}
+			return Err("program doesn't fit in RAM after the start address");
 		}
-		mem
+		self.program_counter = self.memory.start_address;
+		self.load(code);
+		Ok(self)
 	}
 
-	pub fn load_code(&mut self, code: Vec<u8>) -> &mut Self
+	pub fn seed_rng(&mut self, seed: u64)
 	{
-		self.program_counter = 0x200;
-		self.load(code);
-		self
+		self.rng = Pcg32::seed_from_u64(seed);
+		self.rng_seed = seed;
+		self.rng_draws = 0;
 	}
 
-	pub fn load_code_eti(&mut self, code: Vec<u8>) -> &mut Self
+	/// Puts the generator back where it was after `draws` values from `seed`
+	pub(crate) fn restore_rng(&mut self, seed: u64, draws: u64)
 	{
-		self.program_counter = 0x600;
-		self.load(code);
-		self
+		self.seed_rng(seed);
+		self.rng.advance(draws);
+		self.rng_draws = draws;
 	}
 
-	fn load(&mut self, code: Vec<u8>)
+	fn load(&mut self, code: &[u8])
 	{
+		#[cfg(feature = "print")]
 		println!("Loading Program, Length: {}", code.len());
-		for (i, code_line) in code.iter().enumerate()
-		{
-			self.ram[i + self.program_counter] = *code_line;
-		}
+		self.ram[self.program_counter..self.program_counter + code.len()].copy_from_slice(code);
 	}
 
+	/// Lets a program waiting after DXYN continue, without touching the timers
 	pub fn vblank(&mut self)
 	{
 		self.wait_for_vblank = false;
 	}
 
+	#[cfg(feature = "std")]
 	pub fn print_display(&self)
 	{
-		if self.high_res
+		for y in 0..self.display.height()
 		{
-			self.print_display_high_res();
-		}
-		else
-		{
-			self.print_display_low_res();
+			let line: String = (0..self.display.width())
+				.map(|x| if self.display.is_lit(x, y) { '#' } else { ' ' })
+				.collect();
+			println!("{}", line);
 		}
 	}
-	fn print_display_low_res(&self)
+
+	pub fn high_res(&self) -> bool
 	{
-		for row in self.display.iter().take(32)
-		{
-			let d = format!("{:0>64}", format!("{:b}", row >> 64))
-				.replace("0", " ")
-				.replace("1", "#");
-			println!("{}", d);
-		}
+		self.display.width() == DISPLAY_WIDTH_HIGHRES
 	}
 
-	fn print_display_high_res(&self)
+	/// Switches between 64x32 and 128x64, keeping the display contents like SUPER-CHIP does
+	pub fn set_high_res(&mut self, high_res: bool)
 	{
-		for row in self.display
+		if high_res
 		{
-			let d = format!("{:0>128}", format!("{:b}", row))
-				.replace("0", " ")
-				.replace("1", "#");
-			println!("{}", d);
+			self.display
+				.set_resolution(DISPLAY_WIDTH_HIGHRES, DISPLAY_HEIGHT_HIGHRES);
+		}
+		else
+		{
+			self.display.set_resolution(DISPLAY_WIDTH, DISPLAY_HEIGHT);
 		}
 	}
 
@@ -175,6 +229,7 @@ impl Chip8
 		self.reg_i = 0;
 	}
 
+	#[cfg(feature = "std")]
 	pub fn start(&mut self)
 	{
 		while !self.is_halted
@@ -189,12 +244,14 @@ impl Chip8
 		}
 	}
 
+	#[cfg(feature = "std")]
 	pub fn run(&mut self, ticks: usize)
 	{
 		for _ in 0..ticks
 		{
-			if self.program_counter >= self.ram.len()
+			if self.program_counter >= self.memory.ram_size
 			{
+				#[cfg(feature = "print")]
 				println!("Done!");
 				self.is_halted = true;
 				break;
@@ -203,24 +260,67 @@ impl Chip8
 		}
 	}
 
-	pub fn tick(&mut self)
+	/// Runs a single 60Hz frame: up to `ticks` instructions followed by one timer update.
+	/// Timing is left entirely to the caller, so the same inputs always produce the same frame.
+	pub fn run_frame(&mut self, ticks: usize)
 	{
 		#[cfg(feature = "tracing")]
-		let _ = info_span!("Tick").entered();
-
-		if !self.wait_for_vblank
+		let _ = info_span!("Frame").entered();
+		for _ in 0..ticks
 		{
-			self.process_instructions();
-			self.program_counter += 2;
+			if self.is_halted || self.wait_for_vblank
+			{
+				break;
+			}
+			if self.program_counter >= self.memory.ram_size
+			{
+				#[cfg(feature = "print")]
+				println!("Done!");
+				self.is_halted = true;
+				break;
+			}
+			self.step();
 		}
+		self.process_timers();
+		self.vblank();
+	}
+
+	/// Executes one instruction, updating the timers from the wall clock
+	#[cfg(feature = "std")]
+	pub fn tick(&mut self)
+	{
+		self.step();
 		if let Ok(el) = self.timer.elapsed()
 			&& el.as_millis() > 16
 		{
 			self.process_timers();
+			self.vblank();
 		}
 	}
 
-	fn process_timers(&mut self)
+	/// Executes one instruction without touching the timers
+	pub fn step(&mut self)
+	{
+		#[cfg(feature = "tracing")]
+		let _ = info_span!("Tick").entered();
+
+		if !self.wait_for_vblank
+		{
+			let Some(instruction) = self.instruction_at(self.program_counter)
+			else
+			{
+				// Running off the end of RAM
+				self.is_halted = true;
+				return;
+			};
+			self.process_instructions(instruction);
+			// Jumps to address 0 leave the counter wrapped below zero until here
+			self.program_counter = self.program_counter.wrapping_add(2);
+		}
+	}
+
+	/// Decrements the delay and sound timers. Hosts driving the machine with `step` call this at 60Hz.
+	pub fn process_timers(&mut self)
 	{
 		if self.reg_dt > 0
 		{
@@ -230,10 +330,9 @@ impl Chip8
 		{
 			self.reg_st -= 1;
 		}
-		self.timer = SystemTime::now();
-		#[cfg(not(feature = "no-vblank"))]
+		#[cfg(feature = "std")]
 		{
-			self.wait_for_vblank = true;
+			self.timer = SystemTime::now();
 		}
 	}
 
@@ -242,15 +341,18 @@ impl Chip8
 		self.keys[key] = state;
 	}
 
-	fn process_instructions(&mut self)
+	/// The big endian instruction stored at `addr`, if it fits in RAM
+	pub fn instruction_at(&self, addr: usize) -> Option<u16>
+	{
+		let bytes = self.ram[..self.memory.ram_size].get(addr..addr.checked_add(2)?)?;
+		Some(((bytes[0] as u16) << 8) | bytes[1] as u16)
+	}
+
+	fn process_instructions(&mut self, instruction: u16)
 	{
 		#[cfg(feature = "tracing")]
 		let _ = info_span!("Process Instructions").entered();
-		let b1 = &self.ram[self.program_counter];
-		let b2 = &self.ram[self.program_counter + 1];
-		let instruction = ((*b1 as u16) << 8) + (*b2 as u16);
-
-		let g = b1 >> 4;
+		let g = instruction >> 12;
 
 		#[cfg(feature = "print")]
 		print!("[{:#x}] {:#x}: ", self.program_counter, instruction);
@@ -273,7 +375,7 @@ impl Chip8
 			0xD => self.instruction_draw(instruction),
 			0xE => self.instruction_key(instruction),
 			0xF => self.instruction_f(instruction),
-			_ => println!("Unimplemented isntruction: {:#x}", instruction),
+			_ => unreachable!(),
 		}
 
 		#[cfg(feature = "print")]
@@ -314,7 +416,7 @@ impl Chip8
 				}
 				else
 				{
-					self.program_counter -= 2;
+					self.program_counter = self.program_counter.wrapping_sub(2);
 				}
 			}
 			0x15 =>
@@ -336,14 +438,14 @@ impl Chip8
 				//Set VI
 				#[cfg(feature = "print")]
 				println!("SET  VI to V{}", reg);
-				self.reg_i += self.registers[reg as usize] as u16;
+				self.reg_i = self.reg_i.wrapping_add(self.registers[reg as usize] as u16);
 			}
 			0x29 =>
 			{
 				//Set VI to Digit
 				#[cfg(feature = "print")]
 				println!("SET  VI to Digit of V{}", reg);
-				self.reg_i = self.registers[reg as usize] as u16 * 5;
+				self.reg_i = self.digit_address(self.registers[reg as usize]);
 			}
 			0x33 =>
 			{
@@ -351,9 +453,12 @@ impl Chip8
 				#[cfg(feature = "print")]
 				println!("SET  VI to Digit of V{}", reg);
 				let vx = self.registers[reg as usize];
-				self.ram[self.reg_i as usize] = vx / 100;
-				self.ram[self.reg_i as usize + 1] = (vx / 10) - ((vx / 100) * 10);
-				self.ram[self.reg_i as usize + 2] = vx - ((vx / 10) * 10);
+				let digits = [vx / 100, (vx / 10) - ((vx / 100) * 10), vx - ((vx / 10) * 10)];
+				for (offset, digit) in digits.into_iter().enumerate()
+				{
+					let addr = self.address_i(offset);
+					self.ram[addr] = digit;
+				}
 			}
 			0x55 =>
 			{
@@ -363,23 +468,20 @@ impl Chip8
 				let vx = reg as usize + 1;
 				for r in 0..vx
 				{
-					let i = self.reg_i as usize + r;
+					let i = self.address_i(r);
 					self.ram[i] = self.registers[r];
 				}
-				self.reg_i += vx as u16;
+				if self.quirks.memory_increment_i
+				{
+					self.reg_i = self.reg_i.wrapping_add(vx as u16);
+				}
 			}
 			0x65 =>
 			{
 				//Read
 				#[cfg(feature = "print")]
 				println!("READ  V0 thru V{} from ram", reg);
-				let vx = reg as usize + 1;
-				for r in 0..vx
-				{
-					let i = self.reg_i as usize + r;
-					self.registers[r] = self.ram[i];
-				}
-				self.reg_i += vx as u16;
+				self.load_registers(reg as usize + 1);
 			}
 			_ => (),
 		}
@@ -389,7 +491,7 @@ impl Chip8
 	{
 		let reg = (instruction & 0x0F00) >> 8;
 		let mode = instruction & 0x00FF;
-		let k = self.registers[reg as usize];
+		let k = self.registers[reg as usize] & 0xF;
 		match mode
 		{
 			0x9E if self.keys[k as usize] =>
@@ -403,30 +505,6 @@ impl Chip8
 			_ => (),
 		};
 	}
-	fn get_display_height(&self) -> usize
-	{
-		if self.high_res
-		{
-			DISPLAY_HEIGHT_HIGHRES
-		}
-		else
-		{
-			DISPLAY_HEIGHT
-		}
-	}
-
-	fn get_display_width(&self) -> usize
-	{
-		if self.high_res
-		{
-			DISPLAY_WIDTH_HIGHRES
-		}
-		else
-		{
-			DISPLAY_WIDTH
-		}
-	}
-
 	fn instruction_draw(&mut self, instruction: u16)
 	{
 		let regx = (instruction & 0x0F00) >> 8;
@@ -439,7 +517,7 @@ impl Chip8
 		self.registers[0xF] = 0;
 		self.need_draw = true;
 		self.draw_sprite(x, y, n);
-		#[cfg(not(feature = "no-vblank"))]
+		if self.quirks.display_wait && !self.high_res()
 		{
 			self.wait_for_vblank = true;
 		}
@@ -447,68 +525,52 @@ impl Chip8
 
 	fn draw_sprite(&mut self, x: u8, y: u8, sprite_height: u16)
 	{
-		let slice = self.ram.iter().skip(self.reg_i as usize).take(sprite_height as usize);
-		let screen_height = self.get_display_height();
-		let mut s_y = (y as usize) % screen_height;
-		let x = x % (self.get_display_width() as u8);
-		for row in slice
+		let screen_height = self.display.height();
+		let y = (y as usize) % screen_height;
+		let x = x % (self.display.width() as u8);
+		for offset in 0..sprite_height as usize
 		{
-			let data = self.translate_sprite_row_clipped(*row, x);
-			let orig = self.display[s_y];
-			self.display[s_y] = orig ^ data;
-
-			if self.display[s_y] != orig | data
+			let s_y = y + offset;
+			if s_y >= screen_height && !self.quirks.wrap_sprites
 			{
-				self.registers[0xF] = 1;
+				break;
 			}
-
-			s_y += 1;
-			if s_y >= screen_height
+			let row = self.ram[self.address_i(offset)];
+			let data = if self.quirks.wrap_sprites
 			{
-				break;
+				self.translate_sprite_row(row, x)
+			}
+			else
+			{
+				self.translate_sprite_row_clipped(row, x)
+			};
+			if self.display.xor_row(0, s_y % screen_height, data)
+			{
+				self.registers[0xF] = 1;
 			}
-			s_y %= screen_height;
 		}
 	}
 
-	// #[cfg(all(feature = "chip8", not(feature = "schip")))]
+	/// Clipping happens in `Framebuffer::xor_row`, which drops anything past the right edge
 	fn translate_sprite_row_clipped(&self, row: u8, x: u8) -> u128
 	{
-		if self.high_res
-		{
-			let row = row as u128;
-			(row << (DISPLAY_WIDTH_HIGHRES - SPRITE_WIDTH)) >> x as u128
-		}
-		else
-		{
-			let row = row as u64;
-			let row = (row << (DISPLAY_WIDTH - SPRITE_WIDTH)) >> x as u64;
-			(row as u128) << DISPLAY_WIDTH
-		}
-	}
-
-	#[allow(dead_code)]
-	const fn get_translation(value: u8, size: usize) -> u32
-	{
-		let screen_width = size as u32;
-		screen_width - ((value as u32 + (SPRITE_WIDTH as u32)) % screen_width)
+		((row as u128) << (MAX_WIDTH - SPRITE_WIDTH)) >> x
 	}
 
-	#[allow(dead_code)]
-	// #[cfg(feature = "schip")]
+	/// Like `translate_sprite_row_clipped`, with the part past the right edge moved to the left edge
 	fn translate_sprite_row(&self, row: u8, x: u8) -> u128
 	{
-		if self.high_res
+		let (x, width) = (x as usize, self.display.width());
+		let sprite = (row as u128) << (MAX_WIDTH - SPRITE_WIDTH);
+		let wrapped = if x + SPRITE_WIDTH > width
 		{
-			let res = row as u128;
-			res.rotate_left(Self::get_translation(x, DISPLAY_WIDTH_HIGHRES))
+			sprite << (width - x)
 		}
 		else
 		{
-			let mut res = row as u64;
-			res = res.rotate_left(Self::get_translation(x, DISPLAY_WIDTH));
-			(res as u128) << DISPLAY_WIDTH
-		}
+			0
+		};
+		(sprite >> x) | wrapped
 	}
 
 	fn instruction_rand(&mut self, instruction: u16)
@@ -523,27 +585,17 @@ impl Chip8
 
 	fn get_rng(&mut self) -> u8
 	{
+		self.rng_draws += 1;
 		self.rng.next_u32() as u8
 	}
 
 	fn instruction_jump_offset(&mut self, instruction: u16)
 	{
 		let addr = instruction & 0x0FFF;
-		#[cfg(all(feature = "chip8", not(feature = "schip")))]
-		{
-			#[cfg(feature = "print")]
-			println!("JUMP {} + Vx", addr);
-			self.program_counter = (addr + self.registers[0] as u16) as usize;
-			self.program_counter -= 2;
-		}
-		#[cfg(feature = "schip")]
-		{
-			#[cfg(feature = "print")]
-			println!("JUMP {} + Vx", addr);
-			let reg = (addr & 0xF00) >> 8;
-			self.program_counter = (addr + self.registers[reg as usize] as u16) as usize;
-			self.program_counter -= 2;
-		}
+		let reg = if self.quirks.jump_vx { (addr & 0xF00) >> 8 } else { 0 };
+		#[cfg(feature = "print")]
+		println!("JUMP {} + V{}", addr, reg);
+		self.jump(addr + self.registers[reg as usize] as u16);
 	}
 
 	fn instruction_set_reg_i(&mut self, instruction: u16)
@@ -608,7 +660,10 @@ impl Chip8
 				#[cfg(feature = "print")]
 				println!("OR V{} | V{}", reg, reg2);
 				self.registers[reg as usize] |= self.registers[reg2 as usize];
-				self.registers[0xf] = 0;
+				if self.quirks.logic_reset_vf
+				{
+					self.registers[0xf] = 0;
+				}
 			}
 			0x2 =>
 			{
@@ -616,7 +671,10 @@ impl Chip8
 				#[cfg(feature = "print")]
 				println!("AND V{} & V{}", reg, reg2);
 				self.registers[reg as usize] &= self.registers[reg2 as usize];
-				self.registers[0xf] = 0;
+				if self.quirks.logic_reset_vf
+				{
+					self.registers[0xf] = 0;
+				}
 			}
 			0x3 =>
 			{
@@ -624,7 +682,10 @@ impl Chip8
 				#[cfg(feature = "print")]
 				println!("XOR V{} ^ V{}", reg, reg2);
 				self.registers[reg as usize] ^= self.registers[reg2 as usize];
-				self.registers[0xf] = 0;
+				if self.quirks.logic_reset_vf
+				{
+					self.registers[0xf] = 0;
+				}
 			}
 			0x4 =>
 			{
@@ -652,18 +713,10 @@ impl Chip8
 				//SHR
 				#[cfg(feature = "print")]
 				println!("SHR V{} >> 1", reg);
-				#[cfg(all(feature = "chip8", not(feature = "schip")))]
-				{
-					let vy = self.registers[reg2 as usize];
-					self.registers[reg as usize] = vy >> 1;
-					self.registers[0xf] = vy & 0x1;
-				}
-				#[cfg(feature = "schip")]
-				{
-					let vx = self.registers[reg as usize];
-					self.registers[reg as usize] = vx >> 1;
-					self.registers[0xf] = vx & 0x1;
-				}
+				let src = if self.quirks.shift_vx { reg } else { reg2 };
+				let v = self.registers[src as usize];
+				self.registers[reg as usize] = v >> 1;
+				self.registers[0xf] = v & 0x1;
 			}
 			0x7 =>
 			{
@@ -680,20 +733,12 @@ impl Chip8
 				//SHL
 				#[cfg(feature = "print")]
 				println!("SHL V{} << 1", reg);
-				#[cfg(all(feature = "chip8", not(feature = "schip")))]
-				{
-					let vy = self.registers[reg2 as usize];
-					self.registers[reg as usize] = vy << 1;
-					self.registers[0xf] = (vy & 0x80) >> 7;
-				}
-				#[cfg(feature = "schip")]
-				{
-					let vx = self.registers[reg as usize];
-					self.registers[reg as usize] = vx << 1;
-					self.registers[0xf] = (vx & 0x80) >> 7;
-				}
+				let src = if self.quirks.shift_vx { reg } else { reg2 };
+				let v = self.registers[src as usize];
+				self.registers[reg as usize] = v << 1;
+				self.registers[0xf] = (v & 0x80) >> 7;
 			}
-			_ => panic!("Invalid bitwise op"),
+			_ => (),
 		}
 	}
 
@@ -741,10 +786,27 @@ impl Chip8
 		let addr = instruction & 0x0FFF;
 		#[cfg(feature = "print")]
 		println!("Call {:#x}", addr);
+		self.call(addr);
+	}
+
+	/// Pushes the current address and jumps to `addr`, halting when the stack is full
+	pub(crate) fn call(&mut self, addr: u16)
+	{
+		// Entry 0 is never used, so 15 calls can be nested
+		if self.stack_pointer + 1 >= self.stack.len()
+		{
+			self.is_halted = true;
+			return;
+		}
 		self.stack_pointer += 1;
 		self.stack[self.stack_pointer] = self.program_counter as u16;
-		self.program_counter = addr as usize;
-		self.program_counter -= 2;
+		self.jump(addr);
+	}
+
+	/// Sets the counter so `step` moves on to `addr`
+	pub(crate) fn jump(&mut self, addr: u16)
+	{
+		self.program_counter = (addr as usize).wrapping_sub(2);
 	}
 
 	fn instruction_jump(&mut self, instruction: u16)
@@ -758,31 +820,33 @@ impl Chip8
 				self.is_halted = true;
 			}
 		}
-		self.program_counter = addr;
-		self.program_counter -= 2;
+		self.jump(addr as u16);
 	}
 
 	fn instruction_zero(&mut self, instruction: u16)
 	{
-		let mode = instruction & 0x00FF;
+		// Anything with a nonzero second nibble calls machine code, which can't be run
+		let mode = instruction & 0x0FFF;
 		match mode
 		{
-			0xC0..=0xCF => self.instruction_scroll_display_down((mode & 0x0F) as u8),
-			0xE0 => self.instruction_clear(),
-			0xEE => self.instruction_ret(),
-			0xFB => self.instruction_scoll_display_right(),
-			0xFC => self.instruction_scoll_display_left(),
-			0xFD =>
+			0x0C0..=0x0CF => self.instruction_scroll_display_down((mode & 0x0F) as u8),
+			0x0E0 => self.instruction_clear(),
+			0x0EE => self.instruction_ret(),
+			0x0FB => self.instruction_scoll_display_right(),
+			0x0FC => self.instruction_scoll_display_left(),
+			0x0FD =>
 			{
 				self.is_halted = true;
 			}
-			0xFF =>
+			0x0FF =>
 			{
-				self.high_res = true;
+				self.set_high_res(true);
+				self.need_draw = true;
 			}
-			0xFE =>
+			0x0FE =>
 			{
-				self.high_res = false;
+				self.set_high_res(false);
+				self.need_draw = true;
 			}
 			_ =>
 			{
@@ -793,44 +857,71 @@ impl Chip8
 
 	fn instruction_scroll_display_down(&mut self, lines: u8)
 	{
-		let height = self.get_display_height();
-		let mut scrolled = [0; 64];
-		let lines = lines as usize;
-		for (i, line) in self.display.iter().enumerate().take(height - lines)
-		{
-			scrolled[i + lines] = *line;
-		}
-		self.display = scrolled;
+		self.display.scroll_down(lines as usize);
+		self.need_draw = true;
 	}
+
 	fn instruction_scoll_display_left(&mut self)
 	{
-		for line in &mut self.display
-		{
-			*line = *line << 4;
-		}
+		self.display.scroll_left(4);
+		self.need_draw = true;
 	}
 
 	fn instruction_scoll_display_right(&mut self)
 	{
-		for line in &mut self.display
-		{
-			*line = *line >> 4;
-		}
+		self.display.scroll_right(4);
+		self.need_draw = true;
 	}
+
 	fn instruction_clear(&mut self)
 	{
 		#[cfg(feature = "print")]
 		println!("CLS");
 		self.need_draw = true;
-		self.wait_for_vblank;
-		self.display = [0; 64];
+		self.display.clear();
 	}
 
 	fn instruction_ret(&mut self)
 	{
 		#[cfg(feature = "print")]
 		println!("RET");
+		self.ret();
+	}
+
+	/// Returns to the address on top of the stack, halting when it is empty
+	pub(crate) fn ret(&mut self)
+	{
+		if self.stack_pointer == 0 || self.stack_pointer >= self.stack.len()
+		{
+			self.is_halted = true;
+			return;
+		}
 		self.program_counter = self.stack[self.stack_pointer] as usize;
 		self.stack_pointer -= 1;
 	}
+
+	/// The address `offset` bytes past I, wrapping around the end of RAM
+	fn address_i(&self, offset: usize) -> usize
+	{
+		(self.reg_i as usize + offset) % self.memory.ram_size
+	}
+
+	/// FX29: where the font sprite for the low nibble of `value` is
+	pub(crate) fn digit_address(&self, value: u8) -> u16
+	{
+		(self.memory.font_address + (value & 0xF) as usize * 5) as u16
+	}
+
+	/// FX65: reads V0 to V(count - 1) from memory at I
+	pub(crate) fn load_registers(&mut self, count: usize)
+	{
+		for r in 0..count
+		{
+			self.registers[r] = self.ram[self.address_i(r)];
+		}
+		if self.quirks.memory_increment_i
+		{
+			self.reg_i = self.reg_i.wrapping_add(count as u16);
+		}
+	}
 }
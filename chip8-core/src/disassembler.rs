@@ -0,0 +1,99 @@
+use core::fmt;
+
+use crate::quirks::Quirks;
+
+/// Formats an instruction using Cowgod's mnemonics, unknown opcodes are shown as raw data
+#[cfg(feature = "std")]
+pub fn disassemble(instruction: u16, quirks: Quirks) -> String
+{
+	Disassembly { instruction, quirks }.to_string()
+}
+
+/// An instruction that formats as its mnemonic, without needing an allocator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disassembly
+{
+	pub instruction: u16,
+	/// Quirks of the machine running it, `jump_vx` changes which register BNNN adds
+	pub quirks: Quirks,
+}
+
+impl fmt::Display for Disassembly
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		let instruction = self.instruction;
+		let x = (instruction & 0x0F00) >> 8;
+		let y = (instruction & 0x00F0) >> 4;
+		let n = instruction & 0x000F;
+		let kk = instruction & 0x00FF;
+		let nnn = instruction & 0x0FFF;
+		let data = |f: &mut fmt::Formatter<'_>| write!(f, "DW   {:#06x}", instruction);
+
+		match instruction >> 12
+		{
+			0x0 => match nnn
+			{
+				0x0C0..=0x0CF => write!(f, "SCD  {}", n),
+				0x0E0 => f.write_str("CLS"),
+				0x0EE => f.write_str("RET"),
+				0x0FB => f.write_str("SCR"),
+				0x0FC => f.write_str("SCL"),
+				0x0FD => f.write_str("EXIT"),
+				0x0FE => f.write_str("LOW"),
+				0x0FF => f.write_str("HIGH"),
+				_ => write!(f, "SYS  {:#05x}", nnn),
+			},
+			0x1 => write!(f, "JP   {:#05x}", nnn),
+			0x2 => write!(f, "CALL {:#05x}", nnn),
+			0x3 => write!(f, "SE   V{:X}, {:#04x}", x, kk),
+			0x4 => write!(f, "SNE  V{:X}, {:#04x}", x, kk),
+			0x5 if n == 0 => write!(f, "SE   V{:X}, V{:X}", x, y),
+			0x6 => write!(f, "LD   V{:X}, {:#04x}", x, kk),
+			0x7 => write!(f, "ADD  V{:X}, {:#04x}", x, kk),
+			0x8 =>
+			{
+				let op = match n
+				{
+					0x0 => "LD  ",
+					0x1 => "OR  ",
+					0x2 => "AND ",
+					0x3 => "XOR ",
+					0x4 => "ADD ",
+					0x5 => "SUB ",
+					0x6 => "SHR ",
+					0x7 => "SUBN",
+					0xE => "SHL ",
+					_ => return data(f),
+				};
+				write!(f, "{} V{:X}, V{:X}", op, x, y)
+			}
+			0x9 if n == 0 => write!(f, "SNE  V{:X}, V{:X}", x, y),
+			0xA => write!(f, "LD   I, {:#05x}", nnn),
+			0xB if self.quirks.jump_vx => write!(f, "JP   V{:X}, {:#05x}", x, nnn),
+			0xB => write!(f, "JP   V0, {:#05x}", nnn),
+			0xC => write!(f, "RND  V{:X}, {:#04x}", x, kk),
+			0xD => write!(f, "DRW  V{:X}, V{:X}, {}", x, y, n),
+			0xE => match kk
+			{
+				0x9E => write!(f, "SKP  V{:X}", x),
+				0xA1 => write!(f, "SKNP V{:X}", x),
+				_ => data(f),
+			},
+			0xF => match kk
+			{
+				0x07 => write!(f, "LD   V{:X}, DT", x),
+				0x0A => write!(f, "LD   V{:X}, K", x),
+				0x15 => write!(f, "LD   DT, V{:X}", x),
+				0x18 => write!(f, "LD   ST, V{:X}", x),
+				0x1E => write!(f, "ADD  I, V{:X}", x),
+				0x29 => write!(f, "LD   F, V{:X}", x),
+				0x33 => write!(f, "LD   B, V{:X}", x),
+				0x55 => write!(f, "LD   [I], V{:X}", x),
+				0x65 => write!(f, "LD   V{:X}, [I]", x),
+				_ => data(f),
+			},
+			_ => data(f),
+		}
+	}
+}
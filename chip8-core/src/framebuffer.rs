@@ -0,0 +1,198 @@
+pub const MAX_WIDTH: usize = 128;
+pub const MAX_HEIGHT: usize = 64;
+/// XO-CHIP draws to two planes, giving four colors
+pub const MAX_PLANES: usize = 2;
+
+/// The display: up to 128x64 pixels in one or more bitplanes.
+/// Each row of a plane is a `u128` with the leftmost pixel in the top bit, so sprites are drawn a row at a time.
+/// Changing resolution leaves the rows alone, the way SUPER-CHIP keeps the screen when switching modes, so a low
+/// resolution screen is the top left of the high resolution one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Framebuffer
+{
+	width: usize,
+	height: usize,
+	planes: usize,
+	rows: [[u128; MAX_HEIGHT]; MAX_PLANES],
+}
+
+impl Framebuffer
+{
+	pub const fn new(width: usize, height: usize, planes: usize) -> Self
+	{
+		assert!(
+			width <= MAX_WIDTH && height <= MAX_HEIGHT,
+			"resolution is larger than 128x64"
+		);
+		assert!(planes >= 1 && planes <= MAX_PLANES, "unsupported number of planes");
+		Self {
+			width,
+			height,
+			planes,
+			rows: [[0; MAX_HEIGHT]; MAX_PLANES],
+		}
+	}
+
+	pub const fn width(&self) -> usize
+	{
+		self.width
+	}
+
+	pub const fn height(&self) -> usize
+	{
+		self.height
+	}
+
+	pub const fn planes(&self) -> usize
+	{
+		self.planes
+	}
+
+	/// Changes the visible area without touching any pixels
+	pub fn set_resolution(&mut self, width: usize, height: usize)
+	{
+		assert!(
+			width <= MAX_WIDTH && height <= MAX_HEIGHT,
+			"resolution is larger than 128x64"
+		);
+		self.width = width;
+		self.height = height;
+	}
+
+	/// Bits of a row inside the visible width
+	fn visible_bits(&self) -> u128
+	{
+		u128::MAX.checked_shl((MAX_WIDTH - self.width) as u32).unwrap_or(0)
+	}
+
+	/// Turns off every pixel, including those hidden at the current resolution
+	pub fn clear(&mut self)
+	{
+		self.rows = [[0; MAX_HEIGHT]; MAX_PLANES];
+	}
+
+	pub fn is_blank(&self) -> bool
+	{
+		self.rows.iter().flatten().all(|row| *row == 0)
+	}
+
+	/// The color at `x`, `y`, with bit `n` set when the pixel is lit in plane `n`
+	pub fn pixel(&self, x: usize, y: usize) -> u8
+	{
+		(0..self.planes).fold(0, |color, plane| {
+			color | ((((self.rows[plane][y] >> (MAX_WIDTH - 1 - x)) & 1) as u8) << plane)
+		})
+	}
+
+	pub fn is_lit(&self, x: usize, y: usize) -> bool
+	{
+		self.pixel(x, y) != 0
+	}
+
+	/// Lights the pixel in the planes whose bits are set in `color` and turns it off in the others
+	pub fn set_pixel(&mut self, x: usize, y: usize, color: u8)
+	{
+		let bit = 1 << (MAX_WIDTH - 1 - x);
+		for (plane, rows) in self.rows[..self.planes].iter_mut().enumerate()
+		{
+			if color & (1 << plane) != 0
+			{
+				rows[y] |= bit;
+			}
+			else
+			{
+				rows[y] &= !bit;
+			}
+		}
+	}
+
+	/// Row `y` of `plane` as stored, including pixels hidden at the current resolution
+	pub fn row(&self, plane: usize, y: usize) -> u128
+	{
+		self.rows[plane][y]
+	}
+
+	/// Replaces row `y` of `plane` as stored, for restoring saved displays
+	pub fn set_row(&mut self, plane: usize, y: usize, bits: u128)
+	{
+		self.rows[plane][y] = bits;
+	}
+
+	/// The visible rows of `plane`, with hidden pixels masked off
+	pub fn rows(&self, plane: usize) -> impl Iterator<Item = u128> + '_
+	{
+		let visible = self.visible_bits();
+		self.rows[plane][..self.height].iter().map(move |row| row & visible)
+	}
+
+	/// XORs `bits` into row `y` of `plane`, clipped to the visible width. Returns whether a lit pixel was turned off.
+	pub fn xor_row(&mut self, plane: usize, y: usize, bits: u128) -> bool
+	{
+		let bits = bits & self.visible_bits();
+		let row = &mut self.rows[plane][y];
+		let collision = *row & bits != 0;
+		*row ^= bits;
+		collision
+	}
+
+	/// Moves the visible area of every plane down, blanking the rows at the top
+	pub fn scroll_down(&mut self, lines: usize)
+	{
+		let height = self.height;
+		for rows in &mut self.rows[..self.planes]
+		{
+			for y in (0..height).rev()
+			{
+				rows[y] = if y >= lines { rows[y - lines] } else { 0 };
+			}
+		}
+	}
+
+	/// Moves the visible area of every plane left, blanking the columns on the right
+	pub fn scroll_left(&mut self, pixels: usize)
+	{
+		self.scroll_horizontal(|row| row << pixels);
+	}
+
+	/// Moves the visible area of every plane right, blanking the columns on the left
+	pub fn scroll_right(&mut self, pixels: usize)
+	{
+		self.scroll_horizontal(|row| row >> pixels);
+	}
+
+	fn scroll_horizontal(&mut self, shift: impl Fn(u128) -> u128)
+	{
+		let (visible, height) = (self.visible_bits(), self.height);
+		for rows in &mut self.rows[..self.planes]
+		{
+			for row in &mut rows[..height]
+			{
+				*row = (*row & !visible) | (shift(*row & visible) & visible);
+			}
+		}
+	}
+
+	/// Writes the visible area into `out` as RGBA, each pixel a `scale` by `scale` block of `colors[pixel]`.
+	/// `out` must hold `width * scale` by `height * scale` pixels and `colors` one color per combination of planes.
+	pub fn write_rgba(&self, colors: &[[u8; 4]], scale: usize, out: &mut [u8])
+	{
+		let row_bytes = self.width * scale * 4;
+		for y in 0..self.height
+		{
+			// Draw the first copy of the row, then duplicate it for the rest of the block
+			let start = y * scale * row_bytes;
+			for (x, block) in out[start..start + row_bytes].chunks_exact_mut(scale * 4).enumerate()
+			{
+				let color = &colors[self.pixel(x, y) as usize];
+				for pixel in block.chunks_exact_mut(4)
+				{
+					pixel.copy_from_slice(color);
+				}
+			}
+			for copy in 1..scale
+			{
+				out.copy_within(start..start + row_bytes, start + copy * row_bytes);
+			}
+		}
+	}
+}
@@ -0,0 +1,22 @@
+//! The CHIP-8 and SUPER-CHIP interpreter, without any frontend.
+//! Builds without `std` when default features are disabled, leaving timing and output to the host.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod cache;
+pub mod chip8;
+pub mod disassembler;
+pub mod framebuffer;
+pub mod memory;
+pub mod palette;
+pub mod quirks;
+#[cfg(all(test, feature = "std"))]
+mod reference;
+pub mod state;
+#[cfg(all(test, feature = "std"))]
+mod tests;
+
+pub use cache::BlockCache;
+pub use chip8::Chip8;
+pub use framebuffer::Framebuffer;
+pub use memory::MemoryProfile;
+pub use quirks::{Platform, Quirks};
@@ -0,0 +1,157 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// RAM of the largest profile, the size of `Chip8::ram`. The `xochip` feature raises it from 4K to 64K.
+#[cfg(not(feature = "xochip"))]
+pub const MAX_MEMORY: usize = 0x1000;
+#[cfg(feature = "xochip")]
+pub const MAX_MEMORY: usize = 0x10000;
+/// Bytes taken by the hex digit font
+pub const FONT_SIZE: usize = 16 * 5;
+
+/// How much RAM a machine has and where the font, programs and the interpreter's own data live in it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MemoryProfile
+{
+	/// Addresses through I wrap around at this size, and running past it halts the machine
+	pub ram_size: usize,
+	/// Where the hex digit font used by FX29 is stored, 0x000 or 0x050 depending on the interpreter
+	pub font_address: usize,
+	/// Where programs are loaded and execution starts
+	pub start_address: usize,
+	/// Bytes at the top of RAM the original interpreter kept its stack, variables and display in
+	pub reserved: usize,
+}
+
+impl MemoryProfile
+{
+	/// 4K with programs at 0x200, what most ROMs expect
+	pub const STANDARD: Self = Self {
+		ram_size: 0x1000,
+		font_address: 0x000,
+		start_address: 0x200,
+		reserved: 0,
+	};
+	/// 4K with the font at 0x050, where many later interpreters keep it
+	pub const MODERN: Self = Self {
+		font_address: 0x050,
+		..Self::STANDARD
+	};
+	/// The 2K COSMAC VIP, with its interpreter's data in the top 0x160 bytes
+	pub const VIP: Self = Self {
+		ram_size: 0x800,
+		reserved: 0x160,
+		..Self::STANDARD
+	};
+	/// The ETI 660, which starts programs at 0x600
+	pub const ETI_660: Self = Self {
+		start_address: 0x600,
+		..Self::STANDARD
+	};
+	/// XO-CHIP's 64K
+	#[cfg(feature = "xochip")]
+	pub const XO_CHIP: Self = Self {
+		ram_size: 0x10000,
+		..Self::STANDARD
+	};
+
+	pub const PRESETS: &'static [(&'static str, MemoryProfile)] = &[
+		("standard", Self::STANDARD),
+		("modern", Self::MODERN),
+		("vip", Self::VIP),
+		("eti660", Self::ETI_660),
+		#[cfg(feature = "xochip")]
+		("xochip", Self::XO_CHIP),
+	];
+
+	/// Bytes available for a program, between the start address and the reserved area
+	pub const fn rom_space(&self) -> usize
+	{
+		self.ram_size
+			.saturating_sub(self.reserved)
+			.saturating_sub(self.start_address)
+	}
+
+	/// Hex digits needed to show any address, for lining up columns
+	pub const fn address_digits(&self) -> usize
+	{
+		if self.ram_size > 0x1000 { 4 } else { 3 }
+	}
+
+	/// Checks that everything fits in RAM without overlapping
+	pub fn validate(&self) -> Result<(), &'static str>
+	{
+		if self.ram_size < 0x200 || self.ram_size > MAX_MEMORY
+		{
+			return Err("RAM size must be at least 512 bytes and no more than MAX_MEMORY");
+		}
+		if self.reserved > self.ram_size
+		{
+			return Err("reserved area is larger than RAM");
+		}
+		if self.font_address + FONT_SIZE > self.ram_size - self.reserved
+		{
+			return Err("font doesn't fit in RAM");
+		}
+		if self.start_address >= self.ram_size - self.reserved
+		{
+			return Err("start address is outside RAM");
+		}
+		if self.font_address < self.start_address + self.rom_space()
+			&& self.start_address < self.font_address + FONT_SIZE
+		{
+			return Err("font overlaps the program");
+		}
+		Ok(())
+	}
+}
+
+impl Default for MemoryProfile
+{
+	fn default() -> Self
+	{
+		Self::STANDARD
+	}
+}
+
+impl fmt::Display for MemoryProfile
+{
+	/// The preset's name, or the layout for custom profiles
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match Self::PRESETS.iter().find(|(_, profile)| profile == self)
+		{
+			Some((name, _)) => f.write_str(name),
+			None => write!(
+				f,
+				"{}K, font at {:#05x}, start at {:#05x}",
+				self.ram_size / 1024,
+				self.font_address,
+				self.start_address
+			),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl FromStr for MemoryProfile
+{
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Self::PRESETS
+			.iter()
+			.find(|(name, _)| name.eq_ignore_ascii_case(s))
+			.map(|(_, profile)| *profile)
+			.ok_or_else(|| {
+				let names: Vec<_> = Self::PRESETS.iter().map(|(name, _)| *name).collect();
+				format!("unknown memory profile '{}', expected one of: {}", s, names.join(", "))
+			})
+	}
+}
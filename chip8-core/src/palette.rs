@@ -0,0 +1,40 @@
+/// Display colors every frontend offers by name, as background and foreground RGB. The first is the default.
+pub const PRESETS: [(&str, [[u8; 3]; 2]); 4] = [
+	("default", [[89, 0, 36], [255, 0, 100]]),
+	("mono", [[0, 0, 0], [255, 255, 255]]),
+	("amber", [[40, 20, 0], [255, 176, 0]]),
+	("green", [[0, 32, 0], [51, 255, 51]]),
+];
+
+/// Accepts either a preset name or a `background,foreground` pair of hex colors
+#[cfg(feature = "std")]
+pub fn parse(s: &str) -> Result<[[u8; 3]; 2], String>
+{
+	if let Some((_, colors)) = PRESETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(s))
+	{
+		return Ok(*colors);
+	}
+	let Some((background, foreground)) = s.split_once(',')
+	else
+	{
+		let names: Vec<_> = PRESETS.iter().map(|(name, _)| *name).collect();
+		return Err(format!(
+			"unknown palette '{}', expected one of: {} or a BACKGROUND,FOREGROUND hex pair",
+			s,
+			names.join(", ")
+		));
+	};
+	Ok([parse_hex(background.trim())?, parse_hex(foreground.trim())?])
+}
+
+/// Parses an `RRGGBB` color, with or without a leading `#`
+#[cfg(feature = "std")]
+pub fn parse_hex(hex: &str) -> Result<[u8; 3], String>
+{
+	let digits = hex.trim_start_matches('#');
+	let value = u32::from_str_radix(digits, 16)
+		.ok()
+		.filter(|_| digits.len() == 6)
+		.ok_or_else(|| format!("invalid color '{}', expected RRGGBB", hex))?;
+	Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
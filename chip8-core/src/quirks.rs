@@ -0,0 +1,129 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The machine a ROM was written for, each one implying a set of quirks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Platform
+{
+	/// The original COSMAC VIP interpreter
+	#[cfg_attr(feature = "serde", serde(rename = "chip8"))]
+	Chip8,
+	/// SUPER-CHIP 1.1 as implemented by modern interpreters
+	#[cfg_attr(feature = "serde", serde(rename = "schip"))]
+	SuperChip,
+}
+
+impl Platform
+{
+	pub const ALL: [Platform; 2] = [Platform::Chip8, Platform::SuperChip];
+
+	pub fn name(&self) -> &'static str
+	{
+		match self
+		{
+			Platform::Chip8 => "chip8",
+			Platform::SuperChip => "schip",
+		}
+	}
+}
+
+impl Default for Platform
+{
+	/// Follows the `schip` cargo feature so existing builds keep their behaviour
+	fn default() -> Self
+	{
+		if cfg!(feature = "schip")
+		{
+			Platform::SuperChip
+		}
+		else
+		{
+			Platform::Chip8
+		}
+	}
+}
+
+impl fmt::Display for Platform
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		f.write_str(self.name())
+	}
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Platform
+{
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Platform::ALL
+			.into_iter()
+			.find(|p| p.name().eq_ignore_ascii_case(s))
+			.ok_or_else(|| {
+				let names: Vec<_> = Platform::ALL.iter().map(Platform::name).collect();
+				format!("unknown platform '{}', expected one of: {}", s, names.join(", "))
+			})
+	}
+}
+
+/// Behaviour that differs between interpreters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Quirks
+{
+	/// 8XY6/8XYE shift VX in place instead of copying VY first
+	pub shift_vx: bool,
+	/// BXNN jumps to XNN + VX instead of NNN + V0
+	pub jump_vx: bool,
+	/// 8XY1/8XY2/8XY3 reset VF to 0
+	pub logic_reset_vf: bool,
+	/// FX55/FX65 leave I incremented past the last register
+	pub memory_increment_i: bool,
+	/// DXYN wraps sprites around the screen edges instead of clipping them
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub wrap_sprites: bool,
+	/// DXYN waits for the next frame before anything else runs. SUPER-CHIP never waits at high resolution.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub display_wait: bool,
+}
+
+impl Quirks
+{
+	pub const fn for_platform(platform: Platform) -> Self
+	{
+		match platform
+		{
+			Platform::Chip8 => Self {
+				shift_vx: false,
+				jump_vx: false,
+				logic_reset_vf: true,
+				memory_increment_i: true,
+				wrap_sprites: false,
+				display_wait: true,
+			},
+			Platform::SuperChip => Self {
+				shift_vx: true,
+				jump_vx: true,
+				logic_reset_vf: true,
+				memory_increment_i: true,
+				wrap_sprites: false,
+				display_wait: true,
+			},
+		}
+	}
+}
+
+impl Default for Quirks
+{
+	fn default() -> Self
+	{
+		Self::for_platform(Platform::default())
+	}
+}
@@ -0,0 +1,380 @@
+//! A deliberately simple interpreter written straight from the instruction descriptions, used to check `Chip8` one
+//! instruction at a time. It favours being obviously right over being fast: the display is a grid of booleans, the
+//! stack a `Vec` of return addresses and every instruction is decoded from scratch.
+
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use crate::{
+	chip8::{CHIP_DIGITS, Chip8},
+	memory::MemoryProfile,
+	quirks::Quirks,
+};
+
+/// Nested calls allowed before the machine halts, `Chip8` never uses the first stack entry
+const STACK_DEPTH: usize = 15;
+
+pub struct Reference
+{
+	pub pc: usize,
+	pub i: u16,
+	pub v: [u8; 16],
+	/// Addresses to return to, innermost last
+	pub stack: Vec<u16>,
+	pub dt: u8,
+	pub st: u8,
+	pub ram: Vec<u8>,
+	/// Indexed by row then column, only the top left 64x32 is used at low resolution
+	pub pixels: [[bool; 128]; 64],
+	pub high_res: bool,
+	pub halted: bool,
+	pub keys: [bool; 16],
+	pub quirks: Quirks,
+	font_address: usize,
+	/// The same generator as `Chip8`, so CXNN can be compared too
+	rng: Pcg32,
+}
+
+impl Reference
+{
+	/// A machine with the font and `program` where `memory` puts them, matching `Chip8::with_memory`, `seed_rng` and
+	/// `load_code`
+	pub fn new(program: &[u8], quirks: Quirks, seed: u64, memory: &MemoryProfile) -> Self
+	{
+		let mut ram = vec![0; memory.ram_size];
+		ram[memory.font_address..memory.font_address + CHIP_DIGITS.len()].copy_from_slice(&CHIP_DIGITS);
+		ram[memory.start_address..memory.start_address + program.len()].copy_from_slice(program);
+		Self {
+			pc: memory.start_address,
+			i: 0,
+			v: [0; 16],
+			stack: Vec::new(),
+			dt: 0,
+			st: 0,
+			ram,
+			pixels: [[false; 128]; 64],
+			high_res: false,
+			halted: false,
+			keys: [false; 16],
+			quirks,
+			font_address: memory.font_address,
+			rng: Pcg32::seed_from_u64(seed),
+		}
+	}
+
+	fn width(&self) -> usize
+	{
+		if self.high_res { 128 } else { 64 }
+	}
+
+	fn height(&self) -> usize
+	{
+		if self.high_res { 64 } else { 32 }
+	}
+
+	/// RAM at `offset` bytes past I, addresses wrap around the end of RAM
+	fn at_i(&mut self, offset: usize) -> &mut u8
+	{
+		let len = self.ram.len();
+		&mut self.ram[(self.i as usize + offset) % len]
+	}
+
+	pub fn step(&mut self)
+	{
+		if self.pc + 1 >= self.ram.len()
+		{
+			self.halted = true;
+			return;
+		}
+		let op = u16::from_be_bytes([self.ram[self.pc], self.ram[self.pc + 1]]);
+		self.pc += 2;
+
+		let x = ((op >> 8) & 0xF) as usize;
+		let y = ((op >> 4) & 0xF) as usize;
+		let n = (op & 0xF) as usize;
+		let nn = (op & 0xFF) as u8;
+		let nnn = op & 0xFFF;
+		let vx = self.v[x];
+		let vy = self.v[y];
+		match op >> 12
+		{
+			0x0 => match nnn
+			{
+				0x0C0..=0x0CF => self.scroll_down(n),
+				0x0E0 => self.pixels = [[false; 128]; 64],
+				0x0EE => match self.stack.pop()
+				{
+					Some(addr) => self.pc = addr as usize,
+					None => self.halted = true,
+				},
+				0x0FB => self.scroll_right(),
+				0x0FC => self.scroll_left(),
+				0x0FE => self.high_res = false,
+				0x0FF => self.high_res = true,
+				// 00FD exits, and machine code routines can't be run
+				_ => self.halted = true,
+			},
+			0x1 => self.pc = nnn as usize,
+			0x2 =>
+			{
+				if self.stack.len() == STACK_DEPTH
+				{
+					self.halted = true;
+				}
+				else
+				{
+					self.stack.push(self.pc as u16);
+					self.pc = nnn as usize;
+				}
+			}
+			0x3 => self.skip_if(vx == nn),
+			0x4 => self.skip_if(vx != nn),
+			// The VIP doesn't look at the last nibble of 5XY0 and 9XY0
+			0x5 => self.skip_if(vx == vy),
+			0x6 => self.v[x] = nn,
+			0x7 => self.v[x] = vx.wrapping_add(nn),
+			0x8 => self.arithmetic(x, vx, vy, n),
+			0x9 => self.skip_if(vx != vy),
+			0xA => self.i = nnn,
+			0xB =>
+			{
+				let offset = if self.quirks.jump_vx { vx } else { self.v[0] };
+				self.pc = nnn as usize + offset as usize;
+			}
+			0xC => self.v[x] = self.rng.next_u32() as u8 & nn,
+			0xD => self.draw(vx, vy, n),
+			0xE => match nn
+			{
+				0x9E => self.skip_if(self.keys[(vx & 0xF) as usize]),
+				0xA1 => self.skip_if(!self.keys[(vx & 0xF) as usize]),
+				_ => (),
+			},
+			_ => self.misc(x, vx, nn),
+		}
+	}
+
+	fn skip_if(&mut self, condition: bool)
+	{
+		if condition
+		{
+			self.pc += 2;
+		}
+	}
+
+	fn arithmetic(&mut self, x: usize, vx: u8, vy: u8, n: usize)
+	{
+		// The flag is written after the result, so it wins when X is F
+		let (result, flag) = match n
+		{
+			0x0 => (vy, None),
+			0x1 => (vx | vy, self.quirks.logic_reset_vf.then_some(0)),
+			0x2 => (vx & vy, self.quirks.logic_reset_vf.then_some(0)),
+			0x3 => (vx ^ vy, self.quirks.logic_reset_vf.then_some(0)),
+			0x4 => (vx.wrapping_add(vy), Some((vx as u16 + vy as u16 > 0xFF) as u8)),
+			0x5 => (vx.wrapping_sub(vy), Some((vx >= vy) as u8)),
+			0x7 => (vy.wrapping_sub(vx), Some((vy >= vx) as u8)),
+			0x6 | 0xE =>
+			{
+				let source = if self.quirks.shift_vx { vx } else { vy };
+				if n == 0x6
+				{
+					(source >> 1, Some(source & 1))
+				}
+				else
+				{
+					(source << 1, Some(source >> 7))
+				}
+			}
+			_ => return,
+		};
+		self.v[x] = result;
+		if let Some(flag) = flag
+		{
+			self.v[0xF] = flag;
+		}
+	}
+
+	fn misc(&mut self, x: usize, vx: u8, nn: u8)
+	{
+		match nn
+		{
+			0x07 => self.v[x] = self.dt,
+			0x0A => match self.keys.iter().position(|down| *down)
+			{
+				Some(key) => self.v[x] = key as u8,
+				None => self.pc -= 2,
+			},
+			0x15 => self.dt = vx,
+			0x18 => self.st = vx,
+			0x1E => self.i = self.i.wrapping_add(vx as u16),
+			0x29 => self.i = (self.font_address + (vx & 0xF) as usize * 5) as u16,
+			0x33 =>
+			{
+				*self.at_i(0) = vx / 100;
+				*self.at_i(1) = vx / 10 % 10;
+				*self.at_i(2) = vx % 10;
+			}
+			0x55 | 0x65 =>
+			{
+				for r in 0..=x
+				{
+					if nn == 0x55
+					{
+						*self.at_i(r) = self.v[r];
+					}
+					else
+					{
+						self.v[r] = *self.at_i(r);
+					}
+				}
+				if self.quirks.memory_increment_i
+				{
+					self.i = self.i.wrapping_add(x as u16 + 1);
+				}
+			}
+			_ => (),
+		}
+	}
+
+	/// Sprites start at VX, VY wrapped onto the screen and are clipped at the right and bottom edges, or wrapped
+	/// around them with the `wrap_sprites` quirk
+	fn draw(&mut self, vx: u8, vy: u8, rows: usize)
+	{
+		let (width, height, wrap) = (self.width(), self.height(), self.quirks.wrap_sprites);
+		let left = vx as usize % width;
+		let top = vy as usize % height;
+		let mut collision = false;
+		for row in 0..rows
+		{
+			let mut y = top + row;
+			if y >= height
+			{
+				if !wrap
+				{
+					break;
+				}
+				y -= height;
+			}
+			let sprite = *self.at_i(row);
+			for column in 0..8
+			{
+				let mut x = left + column;
+				if x >= width && wrap
+				{
+					x -= width;
+				}
+				if x < width && sprite & (0x80 >> column) != 0
+				{
+					collision |= self.pixels[y][x];
+					self.pixels[y][x] = !self.pixels[y][x];
+				}
+			}
+		}
+		self.v[0xF] = collision as u8;
+	}
+
+	fn scroll_down(&mut self, lines: usize)
+	{
+		for y in (0..self.height()).rev()
+		{
+			self.pixels[y] = if y >= lines
+			{
+				self.pixels[y - lines]
+			}
+			else
+			{
+				[false; 128]
+			};
+		}
+	}
+
+	fn scroll_right(&mut self)
+	{
+		let (width, height) = (self.width(), self.height());
+		for row in &mut self.pixels[..height]
+		{
+			row.copy_within(..width - 4, 4);
+			row[..4].fill(false);
+		}
+	}
+
+	fn scroll_left(&mut self)
+	{
+		let (width, height) = (self.width(), self.height());
+		for row in &mut self.pixels[..height]
+		{
+			row.copy_within(4..width, 0);
+			row[width - 4..width].fill(false);
+		}
+	}
+
+	/// Names the first part of `cpu` that differs, including display contents hidden at low resolution
+	pub fn mismatch(&self, cpu: &Chip8) -> Option<&'static str>
+	{
+		let stack: Vec<u16> = cpu.stack[1..=cpu.stack_pointer].iter().map(|call| call + 2).collect();
+		let display =
+			(0..64).all(|y| (0..128).all(|x| self.pixels[y][x] == ((cpu.display.row(0, y) >> (127 - x)) & 1 == 1)));
+		[
+			("program counter", self.pc == cpu.program_counter),
+			("I", self.i == cpu.reg_i),
+			("registers", self.v == cpu.registers),
+			("stack", self.stack == stack),
+			("timers", (self.dt, self.st) == (cpu.reg_dt, cpu.reg_st)),
+			("halted", self.halted == cpu.is_halted),
+			("resolution", self.high_res == cpu.high_res()),
+			("RAM", self.ram[..] == cpu.ram[..cpu.memory().ram_size]),
+			("display", display),
+		]
+		.into_iter()
+		.find(|(_, same)| !same)
+		.map(|(name, _)| name)
+	}
+}
+
+/// `len` valid instructions loaded at `start`, SUPER-CHIP ones only when `schip` is set. Jumps and calls land on the
+/// program's own instructions, though BNNN and memory writes through I can still end up running data.
+pub fn random_program(rng: &mut Pcg32, len: usize, start: usize, schip: bool) -> Vec<u8>
+{
+	let mut random = |bound: u32| (rng.next_u32() % bound) as u16;
+	let mut program = Vec::with_capacity(len * 2);
+	for _ in 0..len
+	{
+		let target = start as u16 + random(len as u32) * 2;
+		let (x, y) = (random(16) << 8, random(16) << 4);
+		// Small immediates make equality skips likely to go both ways
+		let nn = if random(2) == 0 { random(4) } else { random(256) };
+		let instruction = match random(if schip { 39 } else { 35 })
+		{
+			0 => 0x00E0,
+			1 => 0x00EE,
+			2 => 0x1000 | target,
+			3..=4 => 0x2000 | target,
+			5 => 0x3000 | x | nn,
+			6 => 0x4000 | x | nn,
+			7 => 0x5000 | x | y,
+			8..=9 => 0x6000 | x | nn,
+			10..=11 => 0x7000 | x | nn,
+			12..=19 => 0x8000 | x | y | [0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE][random(9) as usize],
+			20 => 0x9000 | x | y,
+			21 => 0xA000 | (0x400 + random(0xC00)),
+			22 => 0xB000 | target,
+			23 => 0xC000 | x | nn,
+			24..=25 => 0xD000 | x | y | (1 + random(15)),
+			26 => 0xE09E | x,
+			27 => 0xE0A1 | x,
+			28 => 0xF007 | x,
+			29 => 0xF00A | x,
+			30 => 0xF015 | x,
+			31 => 0xF018 | x,
+			32 => [0xF01E, 0xF029][random(2) as usize] | x,
+			33 => 0xF033 | x,
+			34 => [0xF055, 0xF065][random(2) as usize] | x,
+			35 => 0x00C0 | random(16),
+			36 => 0x00FB,
+			37 => 0x00FC,
+			_ => [0x00FD, 0x00FE, 0x00FF][random(3) as usize],
+		};
+		program.extend(instruction.to_be_bytes());
+	}
+	program
+}
@@ -0,0 +1,162 @@
+use crate::{
+	chip8::{Chip8, DISPLAY_HEIGHT_HIGHRES},
+	memory::MemoryProfile,
+};
+
+const VERSION: u8 = 1;
+
+/// Bytes written by `save_state` for a machine with `ram_size` bytes of RAM
+pub const fn state_size(ram_size: usize) -> usize
+{
+	1 // version
+	+ 2 + 1 // program counter, stack pointer
+	+ 16 + 2 // V0-VF, I
+	+ ram_size
+	+ 16 * 2 // stack
+	+ DISPLAY_HEIGHT_HIGHRES * 16
+	+ 1 + 1 + 1 // sound timer, delay timer, flags
+	+ 8 + 8 // RNG seed and draws
+}
+
+/// Bytes written by `save_state` on the standard 4K profile
+pub const STATE_SIZE: usize = state_size(MemoryProfile::STANDARD.ram_size);
+
+const HALTED: u8 = 1;
+const HIGH_RES: u8 = 1 << 1;
+const WAIT_FOR_VBLANK: u8 = 1 << 2;
+
+struct Writer<'a>
+{
+	buf: &'a mut [u8],
+	pos: usize,
+}
+
+impl Writer<'_>
+{
+	fn put(&mut self, bytes: &[u8])
+	{
+		self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+		self.pos += bytes.len();
+	}
+}
+
+struct Reader<'a>
+{
+	buf: &'a [u8],
+}
+
+impl Reader<'_>
+{
+	fn take<const N: usize>(&mut self) -> [u8; N]
+	{
+		self.take_slice(N).try_into().expect("take_slice returns N bytes")
+	}
+
+	fn take_slice(&mut self, len: usize) -> &[u8]
+	{
+		let (bytes, rest) = self.buf.split_at(len);
+		self.buf = rest;
+		bytes
+	}
+}
+
+impl Chip8
+{
+	/// Bytes `save_state` writes and `load_state` expects for this machine's memory profile
+	pub fn state_size(&self) -> usize
+	{
+		state_size(self.memory().ram_size)
+	}
+
+	/// Writes everything needed to resume emulation into `out`, which must hold `state_size()` bytes.
+	/// Quirks, the memory profile and keypad state are left out, they belong to the frontend.
+	pub fn save_state(&self, out: &mut [u8]) -> Result<(), &'static str>
+	{
+		if out.len() < self.state_size()
+		{
+			return Err("buffer is smaller than state_size()");
+		}
+		let ram_size = self.memory().ram_size;
+		// A program counter past the end of RAM halts before anything else runs, and may not fit in 16 bits
+		let past_end = self.program_counter >= ram_size;
+		let program_counter = if past_end { ram_size - 1 } else { self.program_counter };
+		let mut w = Writer { buf: out, pos: 0 };
+		w.put(&[VERSION]);
+		w.put(&(program_counter as u16).to_le_bytes());
+		w.put(&[self.stack_pointer as u8]);
+		w.put(&self.registers);
+		w.put(&self.reg_i.to_le_bytes());
+		w.put(&self.ram[..ram_size]);
+		for entry in self.stack
+		{
+			w.put(&entry.to_le_bytes());
+		}
+		// `Chip8` only draws to the first plane
+		for y in 0..DISPLAY_HEIGHT_HIGHRES
+		{
+			w.put(&self.display.row(0, y).to_le_bytes());
+		}
+		let mut flags = 0;
+		for (set, flag) in [
+			(self.is_halted || past_end, HALTED),
+			(self.high_res(), HIGH_RES),
+			(self.wait_for_vblank, WAIT_FOR_VBLANK),
+		]
+		{
+			if set
+			{
+				flags |= flag;
+			}
+		}
+		w.put(&[self.reg_st, self.reg_dt, flags]);
+		w.put(&self.rng_seed.to_le_bytes());
+		w.put(&self.rng_draws.to_le_bytes());
+		Ok(())
+	}
+
+	/// Restores a state written by `save_state`, leaving the machine untouched if it is invalid
+	pub fn load_state(&mut self, data: &[u8]) -> Result<(), &'static str>
+	{
+		let ram_size = self.memory().ram_size;
+		if data.len() < self.state_size()
+		{
+			return Err("state is truncated");
+		}
+		let mut r = Reader { buf: data };
+		if r.take::<1>() != [VERSION]
+		{
+			return Err("state was saved by an incompatible version");
+		}
+		let program_counter = u16::from_le_bytes(r.take()) as usize;
+		let [stack_pointer] = r.take();
+		if program_counter > ram_size || stack_pointer as usize >= self.stack.len()
+		{
+			return Err("state is corrupt");
+		}
+
+		self.program_counter = program_counter;
+		self.stack_pointer = stack_pointer as usize;
+		self.registers = r.take();
+		self.reg_i = u16::from_le_bytes(r.take());
+		self.ram[..ram_size].copy_from_slice(r.take_slice(ram_size));
+		for entry in &mut self.stack
+		{
+			*entry = u16::from_le_bytes(r.take());
+		}
+		for y in 0..DISPLAY_HEIGHT_HIGHRES
+		{
+			self.display.set_row(0, y, u128::from_le_bytes(r.take()));
+		}
+		let [reg_st, reg_dt, flags] = r.take();
+		self.reg_st = reg_st;
+		self.reg_dt = reg_dt;
+		self.is_halted = flags & HALTED != 0;
+		self.set_high_res(flags & HIGH_RES != 0);
+		self.wait_for_vblank = flags & WAIT_FOR_VBLANK != 0;
+		let seed = u64::from_le_bytes(r.take());
+		let draws = u64::from_le_bytes(r.take());
+		self.restore_rng(seed, draws);
+		self.need_draw = true;
+		Ok(())
+	}
+}
@@ -0,0 +1,1112 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests
+{
+
+	use rand::{Rng, SeedableRng};
+	use rand_pcg::Pcg32;
+
+	use crate::{
+		cache::BlockCache,
+		chip8::{CHIP_DIGITS, Chip8},
+		disassembler::disassemble,
+		framebuffer::Framebuffer,
+		memory::MemoryProfile,
+		palette,
+		quirks::{Platform, Quirks},
+		reference::{Reference, random_program},
+		state::STATE_SIZE,
+	};
+
+	#[test]
+	fn jump()
+	{
+		let mut emu = Chip8::new();
+
+		emu.load_code(vec![0x13, 0x45]).tick();
+
+		assert_eq!(emu.program_counter, 0x345);
+	}
+
+	#[test]
+	fn call()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x23, 0x55]).tick();
+
+		assert_eq!(emu.program_counter, 0x355);
+		assert_eq!(emu.stack_pointer, 1);
+		assert_eq!(emu.stack[emu.stack_pointer], 0x200);
+	}
+
+	#[test]
+	fn ret()
+	{
+		let mut emu = Chip8::new();
+
+		emu.load_code(vec![0x22, 0x04, 0x00, 0xE0, 0x00, 0xEE]);
+		emu.tick();
+		emu.tick();
+
+		assert_eq!(emu.program_counter, 0x200 + 2, "Did not return to the correct addr");
+		assert_eq!(emu.stack_pointer, 0, "Stack pointer is incorrect");
+	}
+
+	#[test]
+	fn skip_eq()
+	{
+		let mut emu = Chip8::new();
+		emu.registers[0x3] = 0x33;
+
+		//Skip
+		emu.load_code(vec![0x33, 0x33]);
+		emu.tick();
+		assert_eq!(emu.program_counter, 0x200 + 4);
+
+		//Dont Skip
+		emu.load_code(vec![0x33, 0x35]).tick();
+		assert_eq!(emu.program_counter, 0x200 + 2);
+	}
+
+	#[test]
+	fn skip_ne()
+	{
+		let mut emu = Chip8::new();
+		emu.registers[0x3] = 0x33;
+
+		//Dont Skip
+		emu.load_code(vec![0x43, 0x33]);
+		emu.tick();
+		assert_eq!(emu.program_counter, 0x200 + 2);
+
+		//Skip
+		emu.load_code(vec![0x43, 0x35]).tick();
+		assert_eq!(emu.program_counter, 0x200 + 4);
+	}
+
+	#[test]
+	fn skip_eq2()
+	{
+		let mut emu = Chip8::new();
+		emu.registers[0x3] = 0x33;
+		emu.registers[0x4] = 0x33;
+
+		//Skip
+		emu.load_code(vec![0x53, 0x40]);
+		emu.tick();
+		assert_eq!(emu.program_counter, 0x200 + 4);
+
+		//Dont Skip
+		emu.load_code(vec![0x53, 0x20]).tick();
+		assert_eq!(emu.program_counter, 0x200 + 2);
+	}
+
+	#[test]
+	fn set_register()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x63, 0x40]).tick();
+
+		assert_eq!(emu.registers[0x3], 0x40);
+	}
+
+	#[test]
+	fn add()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x72, 0x70]);
+		emu.registers[0x2] = 0x01;
+		emu.tick();
+
+		assert_eq!(emu.registers[0x2], 0x01 + 0x70);
+	}
+
+	#[test]
+	fn math_copy()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x70]);
+		emu.registers[0x2] = 0x10;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		assert_eq!(emu.registers[0x7], 0x33, "Original Value Modified");
+		assert_eq!(emu.registers[0x2], 0x33, "Value not copied");
+	}
+
+	#[test]
+	fn math_bit_or()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x71]);
+		emu.registers[0x2] = 0x10;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x10 | 0x33);
+	}
+
+	#[test]
+	fn math_bit_and()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x72]);
+		emu.registers[0x2] = 0x10;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x10 & 0x33);
+	}
+
+	#[test]
+	fn math_bit_xor()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x73]);
+		emu.registers[0x2] = 0x10;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x10 ^ 0x33);
+	}
+
+	#[test]
+	fn math_bit_add()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x74]);
+		emu.registers[0x2] = 0x10;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x10 + 0x33);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	fn math_bit_add_vf_as_vx()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x8F, 0x74]);
+		emu.registers[0xF] = 0x10;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		assert_eq!(emu.registers[0x7], 0x33);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	fn math_bit_add_vf_as_vy()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x87, 0xF4]);
+		emu.registers[0xF] = 0x10;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		assert_eq!(emu.registers[0x7], 0x33 + 0x10);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	fn math_bit_add_carry()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x74]);
+		emu.registers[0x2] = 0xF0;
+		emu.registers[0x7] = 0x33;
+		emu.tick();
+		let r: u16 = (0xF0 + 0x33) & 0x00FF;
+		assert_eq!(emu.registers[0x2], r as u8);
+		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
+	}
+
+	#[test]
+	fn math_bit_sub()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x75]);
+		emu.registers[0x2] = 0x4B;
+		emu.registers[0x7] = 0x2D;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x1E);
+		assert_eq!(emu.registers[0xF], 0x01, "VF incorrectly set");
+	}
+
+	#[test]
+	fn math_bit_sub_borrow()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x75]);
+		emu.registers[0x2] = 0x2D;
+		emu.registers[0x7] = 0x4B;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0xE2);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	#[cfg(feature = "schip")]
+	fn math_bit_shr()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x06]);
+		emu.registers[0x2] = 0x10;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x10 >> 1);
+		assert_eq!(emu.registers[0xF], 0);
+	}
+
+	#[test]
+	#[cfg(feature = "schip")]
+	fn math_bit_shr2()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x06]);
+		emu.registers[0x2] = 0x11;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x11 >> 1);
+		assert_eq!(emu.registers[0xF], 1);
+	}
+
+	#[test]
+	#[cfg(all(feature = "chip8", not(feature = "schip")))]
+	fn math_bit_shr()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x16]);
+		emu.registers[0x1] = 0x10;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x10 >> 1);
+		assert_eq!(emu.registers[0xF], 0);
+	}
+
+	#[test]
+	#[cfg(all(feature = "chip8", not(feature = "schip")))]
+	fn math_bit_shr2()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x16]);
+		emu.registers[0x1] = 0x11;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x11 >> 1);
+		assert_eq!(emu.registers[0xF], 1);
+	}
+
+	#[test]
+	fn math_bit_subn_borrow()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x77]);
+		emu.registers[0x2] = 0x4B;
+		emu.registers[0x7] = 0x2D;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0xE2);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+	#[test]
+	fn math_bit_subn()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x77]);
+		emu.registers[0x2] = 0x2D;
+		emu.registers[0x7] = 0x4B;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x1E);
+		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
+	}
+
+	#[test]
+	#[cfg(feature = "schip")]
+	fn math_bit_shl()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x0E]);
+		emu.registers[0x2] = 0x78;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x78 << 1);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	#[cfg(all(feature = "chip8", not(feature = "schip")))]
+	fn math_bit_shl()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x1E]);
+		emu.registers[0x1] = 0x78;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x78 << 1);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	#[cfg(feature = "schip")]
+	fn math_bit_shl2()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x0E]);
+		emu.registers[0x2] = 0x83;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x83 << 1);
+		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
+	}
+
+	#[test]
+	#[cfg(all(feature = "chip8", not(feature = "schip")))]
+	fn math_bit_shl2()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x82, 0x1E]);
+		emu.registers[0x1] = 0x83;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x83 << 1);
+		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
+	}
+
+	#[test]
+	fn skip_ne2()
+	{
+		let mut emu = Chip8::new();
+		emu.registers[0x3] = 0x33;
+		emu.registers[0x4] = 0x33;
+
+		//Skip
+		emu.load_code(vec![0x93, 0x20]);
+		emu.tick();
+		assert_eq!(emu.program_counter, 0x200 + 4);
+
+		//Dont Skip
+		emu.load_code(vec![0x93, 0x40]).tick();
+		assert_eq!(emu.program_counter, 0x200 + 2);
+	}
+
+	#[test]
+	fn set_i()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xA3, 0x20]);
+		emu.tick();
+		assert_eq!(emu.reg_i, 0x320);
+	}
+
+	#[test]
+	#[cfg(all(feature = "chip8", not(feature = "schip")))]
+	fn jump_offset()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xB3, 0x20]);
+		emu.registers[0x0] = 0x4;
+		emu.tick();
+		assert_eq!(emu.program_counter, 0x320 + 0x4);
+	}
+
+	#[test]
+	#[cfg(feature = "schip")]
+	fn jump_offset()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xB3, 0x20]);
+		emu.registers[0x3] = 0x4;
+		emu.tick();
+		assert_eq!(emu.program_counter, 0x320 + 0x4);
+	}
+
+	#[test]
+	fn rand()
+	{
+		//todo replace with seeded rng
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xC3, 0x0f]);
+		emu.registers[0x3] = 0xff;
+		emu.tick();
+		assert_ne!(emu.registers[0x3], 0xff);
+	}
+
+	#[test]
+	fn draw_sprite()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xD3, 0x11]);
+		emu.registers[0x3] = 62;
+		//Draw a line at (62,0) clipping on the right
+		emu.tick();
+		let expected = 0x3 << 64;
+		println!("{:b}", expected);
+		println!("{:b}", emu.display.row(0, 0));
+		assert_eq!(emu.display.row(0, 0), expected);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	fn draw_sprite_erase()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xD3, 0x11, 0xD4, 0x11]);
+		emu.registers[0x3] = 0;
+		emu.registers[0x4] = 2;
+		emu.tick();
+		emu.vblank();
+		emu.tick();
+		let expected = 0b110011 << (128 - 6);
+		println!("{:b}", expected);
+		println!("{:b}", emu.display.row(0, 0));
+		assert_eq!(emu.display.row(0, 0), expected);
+		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
+	}
+
+	#[test]
+	fn draw_sprite_highres()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xD3, 0x11]);
+		emu.registers[0x3] = 126;
+		emu.set_high_res(true);
+		//Draw a line at (62,0) clipping
+		emu.tick();
+		let expected = 0b11;
+		assert_eq!(emu.display.row(0, 0), expected);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	fn draw_sprite_wraps()
+	{
+		let mut emu = Chip8::new();
+		emu.quirks.wrap_sprites = true;
+		// Two rows of 8 pixels at (60,31), wrapping onto both the left edge and the top row
+		emu.load_code(vec![0xD3, 0x42, 0xFF, 0xFF]);
+		emu.reg_i = 0x202;
+		emu.registers[0x3] = 60;
+		emu.registers[0x4] = 31;
+		emu.tick();
+		let row = 0xFF << 120 >> 60 & u128::MAX << 64 | 0xF << 124;
+		assert_eq!((emu.display.row(0, 31), emu.display.row(0, 0)), (row, row));
+		assert_eq!(emu.display.row(0, 1), 0, "Only two rows should be drawn");
+
+		emu.set_high_res(true);
+		emu.load_code(vec![0xD3, 0x41, 0xFF]);
+		emu.reg_i = 0x202;
+		emu.registers[0x3] = 124;
+		emu.registers[0x4] = 63;
+		emu.vblank();
+		emu.tick();
+		assert_eq!(emu.display.row(0, 63), 0xF | 0xF << 124);
+		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
+	}
+
+	#[test]
+	fn read_dt()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF3, 0x07]);
+		emu.reg_dt = 0x3;
+		emu.tick();
+		assert_eq!(emu.registers[0x3], 0x3);
+	}
+
+	#[test]
+	fn set_dt()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF3, 0x15]);
+		emu.registers[0x3] = 0x3;
+		emu.tick();
+		assert_eq!(emu.reg_dt, 0x3);
+	}
+
+	#[test]
+	fn set_st()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF3, 0x18]);
+		emu.registers[0x3] = 0x3;
+		emu.tick();
+		assert_eq!(emu.reg_st, 0x3);
+	}
+
+	#[test]
+	fn set_vi()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF3, 0x1E]);
+		emu.registers[0x3] = 0x3;
+		emu.tick();
+		assert_eq!(emu.reg_i, 0x3);
+	}
+
+	#[test]
+	fn set_vi_to_digit()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF3, 0x29]);
+		emu.registers[0x3] = 0x3;
+		emu.tick();
+		assert_eq!(emu.reg_i, 0x3 * 5);
+	}
+
+	#[test]
+	fn set_bcd()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF3, 0x33]);
+		emu.registers[0x3] = 128;
+		emu.reg_i = 0x300;
+		emu.tick();
+		assert_eq!(emu.ram[emu.reg_i as usize], 1, "Digit 1 is incorrect");
+		assert_eq!(emu.ram[emu.reg_i as usize + 1], 2, "Digit 2 is incorrect");
+		assert_eq!(emu.ram[emu.reg_i as usize + 2], 8, "Digit 3 is incorrect");
+	}
+
+	#[test]
+	fn store()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF4, 0x55]);
+		for i in 0..0x4
+		{
+			emu.registers[i] = i as u8;
+		}
+		emu.registers[0x5] = 0x9;
+		emu.reg_i = 0x300;
+		emu.tick();
+		for i in 0..0x4
+		{
+			assert_eq!(emu.ram[0x300 + i], i as u8);
+		}
+		assert_eq!(emu.ram[emu.reg_i as usize + 0x5], 0, "Wrote too much");
+		assert_eq!(emu.reg_i, 0x300 + 0x4 + 1, "Register I was not incremented");
+	}
+
+	#[test]
+	fn read()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF4, 0x65]);
+		emu.reg_i = 0x300;
+		for i in 0..0x4
+		{
+			emu.ram[emu.reg_i as usize + i] = i as u8;
+		}
+		emu.ram[emu.reg_i as usize + 0x5] = 0x9;
+		emu.tick();
+		for i in 0..0x3
+		{
+			assert_eq!(emu.registers[i], i as u8);
+		}
+		assert_eq!(emu.registers[0x5], 0, "Wrote too much");
+		assert_eq!(emu.reg_i, 0x300 + 0x4 + 1, "Register I was not incremented");
+	}
+
+	#[test]
+	fn bcd_and_read()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF2, 0x33, 0xF2, 0x65]);
+		emu.registers[0x2] = 128;
+		emu.reg_i = 0x300;
+		emu.tick();
+		emu.tick();
+
+		assert_eq!(emu.registers[0x0], 1, "First Digit");
+		assert_eq!(emu.registers[0x1], 2, "Second Digit");
+		assert_eq!(emu.registers[0x2], 8, "Third Digit");
+	}
+
+	#[test]
+	fn run_frame_timers()
+	{
+		let mut emu = Chip8::new();
+		//Loop forever
+		emu.load_code(vec![0x12, 0x00]);
+		emu.reg_dt = 0x3;
+		emu.reg_st = 0x1;
+		emu.run_frame(10);
+		emu.run_frame(10);
+		assert_eq!(emu.reg_dt, 0x1, "DT should decrement once per frame");
+		assert_eq!(emu.reg_st, 0x0, "ST should stop at zero");
+	}
+
+	#[test]
+	fn run_frame_stops_at_draw()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xD0, 0x01, 0x70, 0x01]);
+		emu.run_frame(10);
+		assert_eq!(emu.program_counter, 0x202, "Should wait for vblank after drawing");
+		emu.run_frame(1);
+		assert_eq!(emu.registers[0x0], 1, "Should resume after vblank");
+	}
+
+	#[test]
+	fn display_wait_quirk()
+	{
+		// Draws once then counts in V0 forever
+		let program = [0xD0, 0x01, 0x70, 0x01, 0x12, 0x02];
+		let mut emu = Chip8::new();
+		emu.quirks.display_wait = false;
+		emu.load_code(program);
+		emu.run_frame(10);
+		assert_eq!(emu.registers[0x0], 5, "Drawing shouldn't wait without the quirk");
+
+		let mut emu = Chip8::new();
+		emu.quirks = Quirks::for_platform(Platform::SuperChip);
+		emu.set_high_res(true);
+		emu.load_code(program);
+		emu.run_frame(10);
+		assert_eq!(emu.registers[0x0], 5, "SUPER-CHIP shouldn't wait at high resolution");
+
+		// Only drawing waits, the timers alone never stall the program
+		let mut emu = Chip8::new();
+		emu.load_code([0x70, 0x01, 0x70, 0x01]);
+		emu.step();
+		emu.process_timers();
+		emu.step();
+		assert_eq!(emu.registers[0x0], 2);
+	}
+
+	#[test]
+	fn quirk_shift_vx()
+	{
+		let mut emu = Chip8::new();
+		emu.quirks = Quirks::for_platform(Platform::SuperChip);
+		emu.load_code(vec![0x82, 0x16]);
+		emu.registers[0x1] = 0x10;
+		emu.registers[0x2] = 0x11;
+		emu.tick();
+		assert_eq!(emu.registers[0x2], 0x11 >> 1, "Should shift VX in place");
+		assert_eq!(emu.registers[0xF], 1);
+	}
+
+	#[test]
+	fn quirk_jump_v0()
+	{
+		let mut emu = Chip8::new();
+		emu.quirks = Quirks::for_platform(Platform::Chip8);
+		emu.load_code(vec![0xB3, 0x20]);
+		emu.registers[0x0] = 0x4;
+		emu.registers[0x3] = 0x8;
+		emu.tick();
+		assert_eq!(emu.program_counter, 0x320 + 0x4);
+	}
+
+	#[test]
+	fn quirk_memory_leave_i()
+	{
+		let mut emu = Chip8::new();
+		emu.quirks.memory_increment_i = false;
+		emu.load_code(vec![0xF4, 0x55]);
+		emu.reg_i = 0x300;
+		emu.tick();
+		assert_eq!(emu.reg_i, 0x300, "Register I should be unchanged");
+	}
+
+	#[test]
+	fn palette_presets_and_pairs()
+	{
+		assert_eq!(palette::parse("Amber"), Ok(palette::PRESETS[2].1));
+		assert_eq!(
+			palette::parse("#102030, 0a0b0c"),
+			Ok([[0x10, 0x20, 0x30], [0x0A, 0x0B, 0x0C]])
+		);
+		assert!(palette::parse("vip").is_err());
+		assert!(palette::parse("102030,fff").is_err());
+	}
+
+	#[test]
+	fn disassemble_known()
+	{
+		let chip8 = Quirks::for_platform(Platform::Chip8);
+		assert_eq!(disassemble(0x00E0, chip8), "CLS");
+		assert_eq!(disassemble(0x1234, chip8), "JP   0x234");
+		assert_eq!(disassemble(0x6A0F, chip8), "LD   VA, 0x0f");
+		assert_eq!(disassemble(0x8126, chip8), "SHR  V1, V2");
+		assert_eq!(disassemble(0xD125, chip8), "DRW  V1, V2, 5");
+		assert_eq!(disassemble(0xF365, chip8), "LD   V3, [I]");
+
+		assert_eq!(disassemble(0xB234, chip8), "JP   V0, 0x234");
+		assert_eq!(
+			disassemble(0xB234, Quirks::for_platform(Platform::SuperChip)),
+			"JP   V2, 0x234"
+		);
+	}
+
+	#[test]
+	fn disassemble_unknown()
+	{
+		let chip8 = Quirks::for_platform(Platform::Chip8);
+		assert_eq!(disassemble(0x8128, chip8), "DW   0x8128");
+		assert_eq!(disassemble(0xE1FF, chip8), "DW   0xe1ff");
+	}
+
+	#[test]
+	fn instruction_at_end_of_ram()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x12, 0x34]);
+		assert_eq!(emu.instruction_at(0x200), Some(0x1234));
+		assert_eq!(emu.instruction_at(0xFFF), None);
+	}
+
+	#[test]
+	fn bad_programs_halt_instead_of_panicking()
+	{
+		// RET with nothing on the stack
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x00, 0xEE]).tick();
+		assert!(emu.is_halted);
+
+		// A subroutine calling itself forever runs out of stack after 15 calls
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x22, 0x00]).run_frame(100);
+		assert!(emu.is_halted);
+		assert_eq!(emu.stack_pointer, 15);
+
+		// The last byte of RAM only holds half an instruction
+		let mut emu = Chip8::new();
+		emu.program_counter = 0xFFF;
+		emu.step();
+		assert!(emu.is_halted);
+	}
+
+	#[test]
+	fn memory_profiles()
+	{
+		let mut emu = Chip8::with_memory(MemoryProfile {
+			font_address: 0x050,
+			..MemoryProfile::ETI_660
+		});
+		// FX29 for digit 1 at 0x600, with the font moved to 0x050
+		emu.load_code(vec![0x60, 0x01, 0xF0, 0x29]);
+		assert_eq!(emu.program_counter, 0x600);
+		emu.run_frame(2);
+		assert_eq!(emu.reg_i, 0x055);
+		assert_eq!(emu.ram[0x055..0x05A], CHIP_DIGITS[5..10]);
+		assert_eq!(emu.ram[0], 0, "The font shouldn't be at 0 too");
+
+		// Writes through I wrap at 2K on the VIP, and running past the end halts there
+		let mut emu = Chip8::with_memory(MemoryProfile::VIP);
+		emu.load_code(vec![0x60, 0xAA, 0xF0, 0x55]);
+		emu.reg_i = 0x800;
+		emu.run_frame(2);
+		assert_eq!((emu.ram[0], emu.ram[0x800]), (0xAA, 0));
+		emu.program_counter = 0x7FE;
+		emu.run_frame(1);
+		assert!(emu.is_halted);
+		// Save states only hold the profile's RAM
+		assert_eq!(emu.state_size(), STATE_SIZE - 0x800);
+		// Programs can't spill into the reserved area
+		let rom_space = MemoryProfile::VIP.rom_space();
+		assert!(emu.try_load_code(vec![0; rom_space + 1]).is_err());
+		assert_eq!(
+			emu.program_counter, 0x800,
+			"A failed load should leave the machine alone"
+		);
+		assert!(emu.try_load_code(vec![0; rom_space]).is_ok());
+
+		assert_eq!(MemoryProfile::VIP.rom_space(), 0x800 - 0x160 - 0x200);
+		assert_eq!(MemoryProfile::STANDARD.address_digits(), 3);
+		for invalid in [
+			MemoryProfile {
+				ram_size: 0x20000,
+				..MemoryProfile::STANDARD
+			},
+			MemoryProfile {
+				font_address: 0x200,
+				..MemoryProfile::STANDARD
+			},
+			MemoryProfile {
+				start_address: 0x700,
+				..MemoryProfile::VIP
+			},
+		]
+		{
+			assert!(invalid.validate().is_err(), "{} should be rejected", invalid);
+			assert!(Chip8::try_with_memory(invalid).is_err());
+		}
+	}
+
+	#[cfg(feature = "xochip")]
+	#[test]
+	fn xo_chip_memory()
+	{
+		// Code can run anywhere in 64K
+		let mut emu = Chip8::with_memory(MemoryProfile::XO_CHIP);
+		emu.ram[0xFFFC..0xFFFE].copy_from_slice(&[0x60, 0x07]);
+		emu.program_counter = 0xFFFC;
+		emu.run_frame(1);
+		assert_eq!(emu.registers[0], 7);
+		assert!(!emu.is_halted);
+		assert_eq!(MemoryProfile::XO_CHIP.address_digits(), 4);
+
+		// Running off the end leaves a program counter that doesn't fit the state, which is saved as halted
+		emu.program_counter = 0xFFFE;
+		emu.ram[0xFFFE..].copy_from_slice(&[0x60, 0x08]);
+		emu.run_frame(1);
+		assert_eq!(emu.program_counter, 0x10000);
+		let mut state = vec![0; emu.state_size()];
+		emu.save_state(&mut state).unwrap();
+		let mut restored = Chip8::with_memory(MemoryProfile::XO_CHIP);
+		restored.load_state(&state).unwrap();
+		assert!(restored.is_halted);
+		assert_eq!((restored.registers[0], restored.ram[0xFFFC]), (8, 0x60));
+	}
+
+	#[test]
+	fn jump_to_address_zero()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0x10, 0x00]).tick();
+		assert_eq!(emu.program_counter, 0);
+		emu.load_code(vec![0xB0, 0x01]).tick();
+		assert_eq!(emu.program_counter, 1);
+	}
+
+	#[test]
+	fn memory_access_wraps_around_ram()
+	{
+		let mut emu = Chip8::new();
+		emu.load_code(vec![0xF3, 0x33, 0xF3, 0x1E, 0xF1, 0x55, 0xD2, 0x22]);
+		emu.registers[0x3] = 234;
+		emu.reg_i = 0xFFE;
+		emu.tick();
+		assert_eq!(&emu.ram[0xFFE..0x1000], &[2, 3]);
+		assert_eq!(emu.ram[0], 4);
+
+		emu.reg_i = 0xFFFF;
+		emu.tick();
+		assert_eq!(emu.reg_i, 233, "I should overflow 16 bits");
+
+		emu.reg_i = 0xFFF;
+		emu.registers[0x0] = 0xAA;
+		emu.registers[0x1] = 0x55;
+		emu.tick();
+		assert_eq!((emu.ram[0xFFF], emu.ram[0]), (0xAA, 0x55));
+
+		// Sprites read past the end of RAM continue from the start
+		emu.reg_i = 0xFFF;
+		emu.tick();
+		assert_eq!(emu.display.row(0, 0) >> 120, 0xAA);
+		assert_eq!(emu.display.row(0, 1) >> 120, 0x55);
+	}
+
+	#[test]
+	fn framebuffer_planes_and_resolution()
+	{
+		let mut display = Framebuffer::new(64, 32, 2);
+		display.set_pixel(0, 0, 0b01);
+		display.set_pixel(63, 31, 0b11);
+		display.set_pixel(1, 0, 0b10);
+		assert_eq!(
+			(display.pixel(0, 0), display.pixel(63, 31), display.pixel(1, 0)),
+			(1, 3, 2)
+		);
+		assert_eq!(display.rows(0).count(), 32);
+		assert!(!display.is_lit(2, 0));
+
+		// Clipped at the right edge, and reporting the lit pixel turned off
+		assert!(display.xor_row(0, 31, 0xFF << 64));
+		assert_eq!(display.row(0, 31), 0xFE << 64);
+
+		display.scroll_right(4);
+		assert_eq!(display.pixel(4, 0), 1);
+		assert_eq!(
+			display.row(0, 31),
+			0xF << 64,
+			"Pixels should drop off the visible width"
+		);
+		display.scroll_down(2);
+		assert_eq!(display.pixel(5, 2), 2);
+
+		let colors = [[0, 0, 0, 255], [255, 0, 0, 255], [0, 255, 0, 255], [255, 255, 255, 255]];
+		let mut rgba = vec![0; 128 * 64 * 4];
+		display.write_rgba(&colors, 2, &mut rgba);
+		let at = |x: usize, y: usize| &rgba[(y * 128 + x) * 4..][..4];
+		assert_eq!(
+			(at(8, 4), at(9, 5), at(10, 4), at(0, 0)),
+			(&colors[1][..], &colors[1][..], &colors[2][..], &colors[0][..])
+		);
+
+		display.set_resolution(128, 64);
+		assert_eq!(
+			display.pixel(4, 2),
+			1,
+			"Low resolution pixels should stay in the top left"
+		);
+		display.clear();
+		assert!(display.is_blank());
+	}
+
+	#[test]
+	fn save_state_round_trip()
+	{
+		// Draws random sprites forever, so the RNG position matters after loading
+		let rom = [0xC0, 0x3F, 0xC1, 0x1F, 0xD0, 0x15, 0x12, 0x00];
+		let mut emu = Chip8::new();
+		emu.seed_rng(7);
+		emu.load_code(rom);
+		for _ in 0..10
+		{
+			emu.run_frame(20);
+		}
+		let mut state = vec![0; STATE_SIZE];
+		emu.save_state(&mut state).unwrap();
+
+		let mut restored = Chip8::new();
+		restored.load_state(&state).unwrap();
+		for _ in 0..10
+		{
+			emu.run_frame(20);
+			restored.run_frame(20);
+		}
+		assert_eq!(restored.display, emu.display);
+		assert_eq!(restored.registers, emu.registers);
+		assert_eq!(restored.program_counter, emu.program_counter);
+	}
+
+	#[test]
+	fn load_state_rejects_bad_data()
+	{
+		let mut emu = Chip8::new();
+		assert!(emu.load_state(&[0; 16]).is_err());
+		assert!(
+			emu.load_state(&vec![0; STATE_SIZE]).is_err(),
+			"Version 0 should be rejected"
+		);
+	}
+
+	/// Runs `code` on the interpreter and through a block cache, checking the machines stay identical every frame
+	fn run_both(code: &[u8], platform: Platform, frames: usize) -> Chip8
+	{
+		let mut interpreted = Chip8::new();
+		let mut cached = Chip8::new();
+		let mut cache = BlockCache::new();
+		for cpu in [&mut interpreted, &mut cached]
+		{
+			cpu.quirks = Quirks::for_platform(platform);
+			cpu.load_code(code);
+		}
+		let (mut expected, mut actual) = (vec![0; STATE_SIZE], vec![0; STATE_SIZE]);
+		for frame in 0..frames
+		{
+			for cpu in [&mut interpreted, &mut cached]
+			{
+				cpu.keys = [false; 16];
+				cpu.keys[frame % 16] = frame % 3 == 0;
+			}
+			interpreted.run_frame(50);
+			cache.run_frame(&mut cached, 50);
+			interpreted.save_state(&mut expected).unwrap();
+			cached.save_state(&mut actual).unwrap();
+			assert!(expected == actual, "{} diverged on frame {}", platform, frame);
+		}
+		cached
+	}
+
+	#[test]
+	fn block_cache_matches_interpreter()
+	{
+		#[rustfmt::skip]
+		let code = [
+			0x00, 0xE0, 0x6A, 0x00,
+			// Arithmetic on random values, then draw the result and read back its digits
+			0xCB, 0x3F, 0xCC, 0x1F, 0x8D, 0xB0, 0x8D, 0xC4, 0x8D, 0xC5, 0x8D, 0xB6, 0x8D, 0xCE, 0x8D, 0xB1,
+			0x8D, 0xC2, 0x8D, 0xB3, 0x8D, 0xC7, 0xFA, 0x29, 0xDB, 0xC5, 0xA3, 0x00, 0xFD, 0x33, 0xF2, 0x65,
+			// Loop 16 times, then test keys, call a subroutine and play with the timers
+			0x7A, 0x01, 0x3A, 0x10, 0x12, 0x04, 0x6A, 0x00, 0xE0, 0x9E, 0x22, 0x40, 0xF0, 0x15, 0xF1, 0x07,
+			0xF1, 0x18, 0xF1, 0x1E, 0x12, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x45, 0x00, 0x65, 0x01, 0x55, 0x60, 0x95, 0x60, 0xE1, 0xA1, 0x00, 0xEE,
+		];
+		for platform in Platform::ALL
+		{
+			let cpu = run_both(&code, platform, 300);
+			assert!(!cpu.display.is_blank());
+		}
+	}
+
+	#[test]
+	fn block_cache_sees_self_modifying_code()
+	{
+		#[rustfmt::skip]
+		let code = [
+			// Call the subroutine, overwrite its first instruction with 7205 (V2 += 5), then call it again
+			0x60, 0x72, 0x61, 0x05, 0x22, 0x10, 0xA2, 0x10, 0xF1, 0x55, 0x22, 0x10, 0x12, 0x0C, 0x00, 0x00,
+			// V2 = 9
+			0x62, 0x09, 0x00, 0xEE,
+		];
+		let cpu = run_both(&code, Platform::Chip8, 2);
+		assert_eq!(cpu.registers[2], 14);
+
+		// The same a step at a time, once the frame has decoded the subroutine
+		let mut cpu = Chip8::new();
+		let mut cache = BlockCache::new();
+		cpu.load_code(code);
+		cache.run_frame(&mut cpu, 5);
+		while cpu.program_counter != 0x20C
+		{
+			cache.step(&mut cpu);
+		}
+		assert_eq!(cpu.registers[2], 14);
+	}
+
+	/// Runs random programs on `Chip8` and the reference interpreter, comparing them after every instruction.
+	/// Programs take turns between the memory profiles. `CHIP8_DIFFERENTIAL_RUNS` sets how many programs are tried per
+	/// platform, for longer runs.
+	#[test]
+	fn matches_reference_interpreter()
+	{
+		let runs = std::env::var("CHIP8_DIFFERENTIAL_RUNS")
+			.ok()
+			.and_then(|runs| runs.parse().ok())
+			.unwrap_or(100);
+		for (platform, wrap_sprites) in Platform::ALL
+			.into_iter()
+			.flat_map(|platform| [(platform, false), (platform, true)])
+		{
+			let quirks = Quirks {
+				wrap_sprites,
+				..Quirks::for_platform(platform)
+			};
+			for seed in 0..runs
+			{
+				let (profile, memory) = MemoryProfile::PRESETS[seed as usize % MemoryProfile::PRESETS.len()];
+				let mut rng = Pcg32::seed_from_u64(seed);
+				let program = random_program(&mut rng, 100, memory.start_address, platform == Platform::SuperChip);
+				let keys = rng.next_u32();
+				let mut cpu = Chip8::with_memory(memory);
+				cpu.quirks = quirks;
+				cpu.seed_rng(seed);
+				cpu.load_code(&program);
+				cpu.keys = core::array::from_fn(|key| keys & (1 << key) != 0);
+				let mut reference = Reference::new(&program, quirks, seed, &memory);
+				reference.keys = cpu.keys;
+
+				let mut trace = Vec::new();
+				for _ in 0..300
+				{
+					if cpu.is_halted
+					{
+						break;
+					}
+					let pc = cpu.program_counter;
+					trace.push(format!(
+						"{:#05x}: {}",
+						pc,
+						disassemble(cpu.instruction_at(pc).unwrap_or(0), quirks)
+					));
+					cpu.step();
+					cpu.vblank();
+					reference.step();
+					if let Some(field) = reference.mismatch(&cpu)
+					{
+						let recent = &trace[trace.len().saturating_sub(8)..];
+						panic!(
+							"{} differs on {} (wrap_sprites {}, {} memory) seed {} after:\n{}",
+							field,
+							platform,
+							wrap_sprites,
+							profile,
+							seed,
+							recent.join("\n")
+						);
+					}
+				}
+			}
+		}
+	}
+}
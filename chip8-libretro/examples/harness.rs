@@ -0,0 +1,127 @@
+//! Loads the built core the way a libretro frontend would and runs a ROM headlessly:
+//! `cargo build -p chip8-libretro && cargo run -p chip8-libretro --example harness -- target/debug/libchip8_libretro.so rom.ch8`
+use std::{
+	env,
+	ffi::c_void,
+	fs, ptr, slice,
+	sync::{
+		Mutex,
+		atomic::{AtomicUsize, Ordering},
+	},
+};
+
+use chip8_libretro::ffi::*;
+use libloading::{Library, Symbol};
+
+static FRAME: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+static AUDIO_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" fn environment(cmd: u32, _data: *mut c_void) -> bool
+{
+	matches!(
+		cmd,
+		RETRO_ENVIRONMENT_SET_PIXEL_FORMAT | RETRO_ENVIRONMENT_SET_VARIABLES
+	)
+}
+
+unsafe extern "C" fn video_refresh(data: *const c_void, width: u32, height: u32, pitch: usize)
+{
+	let mut frame = FRAME.lock().unwrap();
+	frame.clear();
+	for y in 0..height as usize
+	{
+		let row = unsafe { slice::from_raw_parts((data as *const u8).add(y * pitch) as *const u32, width as usize) };
+		frame.extend_from_slice(row);
+	}
+}
+
+unsafe extern "C" fn audio_batch(_data: *const i16, frames: usize) -> usize
+{
+	AUDIO_FRAMES.fetch_add(frames, Ordering::Relaxed);
+	frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+unsafe extern "C" fn input_state(_port: u32, _device: u32, _index: u32, _id: u32) -> i16
+{
+	0
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+	let args: Vec<String> = env::args().collect();
+	let [_, core, rom, rest @ ..] = args.as_slice()
+	else
+	{
+		return Err("usage: harness <core library> <rom> [frames]".into());
+	};
+	let frames: usize = rest.first().map(|f| f.parse()).transpose()?.unwrap_or(600);
+	let rom = fs::read(rom)?;
+
+	unsafe {
+		let core = Library::new(core)?;
+		let api_version: Symbol<extern "C" fn() -> u32> = core.get(b"retro_api_version")?;
+		assert_eq!(api_version(), RETRO_API_VERSION);
+
+		let set_environment: Symbol<extern "C" fn(RetroEnvironment)> = core.get(b"retro_set_environment")?;
+		let set_video_refresh: Symbol<extern "C" fn(RetroVideoRefresh)> = core.get(b"retro_set_video_refresh")?;
+		let set_audio_batch: Symbol<extern "C" fn(RetroAudioSampleBatch)> =
+			core.get(b"retro_set_audio_sample_batch")?;
+		let set_input_poll: Symbol<extern "C" fn(RetroInputPoll)> = core.get(b"retro_set_input_poll")?;
+		let set_input_state: Symbol<extern "C" fn(RetroInputState)> = core.get(b"retro_set_input_state")?;
+		set_environment(environment);
+		set_video_refresh(video_refresh);
+		set_audio_batch(audio_batch);
+		set_input_poll(input_poll);
+		set_input_state(input_state);
+
+		let init: Symbol<extern "C" fn()> = core.get(b"retro_init")?;
+		let load_game: Symbol<unsafe extern "C" fn(*const RetroGameInfo) -> bool> = core.get(b"retro_load_game")?;
+		let run: Symbol<extern "C" fn()> = core.get(b"retro_run")?;
+		let serialize_size: Symbol<extern "C" fn() -> usize> = core.get(b"retro_serialize_size")?;
+		let serialize: Symbol<unsafe extern "C" fn(*mut c_void, usize) -> bool> = core.get(b"retro_serialize")?;
+		let unserialize: Symbol<unsafe extern "C" fn(*const c_void, usize) -> bool> = core.get(b"retro_unserialize")?;
+		let deinit: Symbol<extern "C" fn()> = core.get(b"retro_deinit")?;
+
+		init();
+		let game = RetroGameInfo {
+			path: ptr::null(),
+			data: rom.as_ptr() as *const c_void,
+			size: rom.len(),
+			meta: ptr::null(),
+		};
+		if !load_game(&game)
+		{
+			return Err("the core rejected the ROM".into());
+		}
+		for _ in 0..frames
+		{
+			run();
+		}
+
+		// Running on from a restored state has to produce the same picture
+		let mut state = vec![0_u8; serialize_size()];
+		assert!(serialize(state.as_mut_ptr() as *mut c_void, state.len()));
+		run();
+		let expected = FRAME.lock().unwrap().clone();
+		assert!(unserialize(state.as_ptr() as *const c_void, state.len()));
+		run();
+		assert_eq!(*FRAME.lock().unwrap(), expected, "state did not round trip");
+		deinit();
+	}
+
+	let frame = FRAME.lock().unwrap();
+	let background = frame.last().copied().unwrap_or_default();
+	for row in frame.chunks(128).step_by(2)
+	{
+		let line: String = row.iter().map(|p| if *p == background { ' ' } else { '#' }).collect();
+		println!("{}", line.trim_end());
+	}
+	println!(
+		"{} frames, {} audio frames, save states round trip",
+		frames + 2,
+		AUDIO_FRAMES.load(Ordering::Relaxed)
+	);
+	Ok(())
+}
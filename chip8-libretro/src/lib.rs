@@ -0,0 +1,463 @@
+//! A libretro core wrapping `chip8-core`, so the emulator can run inside RetroArch and other libretro frontends
+pub mod ffi;
+mod options;
+#[cfg(test)]
+mod tests;
+
+use std::{
+	ffi::{c_char, c_void},
+	slice,
+	sync::{Mutex, MutexGuard},
+};
+
+use chip8_core::{
+	Chip8,
+	chip8::{DISPLAY_HEIGHT_HIGHRES, DISPLAY_WIDTH_HIGHRES},
+	state::STATE_SIZE,
+};
+
+use crate::{ffi::*, options::Options};
+
+const WIDTH: usize = DISPLAY_WIDTH_HIGHRES;
+const HEIGHT: usize = DISPLAY_HEIGHT_HIGHRES;
+const FPS: f64 = 60.;
+const SAMPLE_RATE: usize = 44_100;
+const SAMPLES_PER_FRAME: usize = SAMPLE_RATE / FPS as usize;
+/// Samples per half period of the 441Hz square wave played while the sound timer is running
+const BEEP_HALF_PERIOD: usize = 50;
+const BEEP_VOLUME: i16 = 0x1000;
+
+/// RetroPad buttons, laid out for the common 5/7/8/9 movement and 4/6 action keys
+const JOYPAD_LAYOUT: [(u32, usize); 12] = [
+	(RETRO_DEVICE_ID_JOYPAD_UP, 0x5),
+	(RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8),
+	(RETRO_DEVICE_ID_JOYPAD_LEFT, 0x7),
+	(RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x9),
+	(RETRO_DEVICE_ID_JOYPAD_A, 0x6),
+	(RETRO_DEVICE_ID_JOYPAD_B, 0x4),
+	(RETRO_DEVICE_ID_JOYPAD_X, 0x1),
+	(RETRO_DEVICE_ID_JOYPAD_Y, 0x2),
+	(RETRO_DEVICE_ID_JOYPAD_L, 0x3),
+	(RETRO_DEVICE_ID_JOYPAD_R, 0xC),
+	(RETRO_DEVICE_ID_JOYPAD_SELECT, 0x0),
+	(RETRO_DEVICE_ID_JOYPAD_START, 0xF),
+];
+/// The usual 1234/QWER/ASDF/ZXCV keyboard layout, libretro key codes match ASCII for these keys
+const KEYBOARD_LAYOUT: [(u8, usize); 16] = [
+	(b'1', 0x1),
+	(b'2', 0x2),
+	(b'3', 0x3),
+	(b'4', 0xC),
+	(b'q', 0x4),
+	(b'w', 0x5),
+	(b'e', 0x6),
+	(b'r', 0xD),
+	(b'a', 0x7),
+	(b's', 0x8),
+	(b'd', 0x9),
+	(b'f', 0xE),
+	(b'z', 0xA),
+	(b'x', 0x0),
+	(b'c', 0xB),
+	(b'v', 0xF),
+];
+
+struct Callbacks
+{
+	environment: Option<RetroEnvironment>,
+	video_refresh: Option<RetroVideoRefresh>,
+	audio_batch: Option<RetroAudioSampleBatch>,
+	input_poll: Option<RetroInputPoll>,
+	input_state: Option<RetroInputState>,
+}
+
+/// A loaded game
+struct Core
+{
+	cpu: Chip8,
+	rom: Vec<u8>,
+	options: Options,
+	framebuffer: Vec<u32>,
+	audio: Vec<i16>,
+	beep_phase: usize,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+	environment: None,
+	video_refresh: None,
+	audio_batch: None,
+	input_poll: None,
+	input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn callbacks() -> MutexGuard<'static, Callbacks>
+{
+	CALLBACKS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn core() -> MutexGuard<'static, Option<Core>>
+{
+	CORE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl Core
+{
+	fn new(rom: Vec<u8>, options: Options) -> Result<Self, &'static str>
+	{
+		Ok(Self {
+			cpu: Self::machine(&rom, &options)?,
+			rom,
+			options,
+			framebuffer: vec![0; WIDTH * HEIGHT],
+			audio: vec![0; SAMPLES_PER_FRAME * 2],
+			beep_phase: 0,
+		})
+	}
+
+	/// A machine at power on with `rom` loaded, if it fits in the memory profile
+	fn machine(rom: &[u8], options: &Options) -> Result<Chip8, &'static str>
+	{
+		let mut cpu = Chip8::try_with_memory(options.memory)?;
+		cpu.quirks = options.quirks;
+		cpu.try_load_code(rom)?;
+		Ok(cpu)
+	}
+
+	fn reset(&mut self)
+	{
+		// The options in use always fit the ROM, they were checked when applied
+		if let Ok(cpu) = Self::machine(&self.rom, &self.options)
+		{
+			self.cpu = cpu;
+		}
+	}
+
+	fn run(&mut self, callbacks: &Callbacks)
+	{
+		if let Some(environment) = callbacks.environment
+		{
+			let mut updated = false;
+			// SAFETY: the frontend writes a single bool
+			unsafe {
+				environment(
+					RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+					&mut updated as *mut bool as *mut c_void,
+				)
+			};
+			if updated
+			{
+				let mut options = Options::read(environment);
+				// RAM can't be resized under a running program, so a new profile restarts it if the ROM fits
+				if options.memory != self.options.memory
+				{
+					match Self::machine(&self.rom, &options)
+					{
+						Ok(cpu) => self.cpu = cpu,
+						Err(_) => options.memory = self.options.memory,
+					}
+				}
+				self.options = options;
+				self.cpu.quirks = self.options.quirks;
+			}
+		}
+		if let Some(input_poll) = callbacks.input_poll
+		{
+			unsafe { input_poll() };
+		}
+		if let Some(input_state) = callbacks.input_state
+		{
+			self.cpu.keys = read_keypad(input_state);
+		}
+
+		self.cpu.run_frame(self.options.ticks_per_frame);
+
+		self.render();
+		if let Some(video_refresh) = callbacks.video_refresh
+		{
+			unsafe {
+				video_refresh(
+					self.framebuffer.as_ptr() as *const c_void,
+					WIDTH as u32,
+					HEIGHT as u32,
+					WIDTH * size_of::<u32>(),
+				)
+			};
+		}
+		self.mix_audio();
+		if let Some(audio_batch) = callbacks.audio_batch
+		{
+			unsafe { audio_batch(self.audio.as_ptr(), SAMPLES_PER_FRAME) };
+		}
+	}
+
+	/// Draws the display at 128x64, doubling low resolution pixels
+	fn render(&mut self)
+	{
+		let [background, foreground] = self.options.palette;
+		let display = &self.cpu.display;
+		let scale = WIDTH / display.width();
+		for (i, pixel) in self.framebuffer.iter_mut().enumerate()
+		{
+			let lit = display.is_lit(i % WIDTH / scale, i / WIDTH / scale);
+			*pixel = if lit { foreground } else { background };
+		}
+	}
+
+	/// Fills a frame of interleaved stereo samples, beeping while the sound timer is running
+	fn mix_audio(&mut self)
+	{
+		let beeping = self.cpu.reg_st > 0;
+		for frame in self.audio.chunks_exact_mut(2)
+		{
+			let sample = if !beeping
+			{
+				0
+			}
+			else if self.beep_phase < BEEP_HALF_PERIOD
+			{
+				BEEP_VOLUME
+			}
+			else
+			{
+				-BEEP_VOLUME
+			};
+			frame.fill(sample);
+			self.beep_phase = (self.beep_phase + 1) % (BEEP_HALF_PERIOD * 2);
+		}
+	}
+}
+
+fn read_keypad(input_state: RetroInputState) -> [bool; 16]
+{
+	let mut keys = [false; 16];
+	for (button, key) in JOYPAD_LAYOUT
+	{
+		keys[key] |= unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, button) } != 0;
+	}
+	for (code, key) in KEYBOARD_LAYOUT
+	{
+		keys[key] |= unsafe { input_state(0, RETRO_DEVICE_KEYBOARD, 0, code as u32) } != 0;
+	}
+	keys
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> u32
+{
+	RETRO_API_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit()
+{
+	*core() = None;
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_info`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo)
+{
+	let info = unsafe { &mut *info };
+	info.library_name = c"chip-8".as_ptr();
+	info.library_version = c"0.1.0".as_ptr();
+	info.valid_extensions = c"ch8|c8|sc8|xo8".as_ptr();
+	info.need_fullpath = false;
+	info.block_extract = false;
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_av_info`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo)
+{
+	let info = unsafe { &mut *info };
+	info.geometry = RetroGameGeometry {
+		base_width: WIDTH as u32,
+		base_height: HEIGHT as u32,
+		max_width: WIDTH as u32,
+		max_height: HEIGHT as u32,
+		aspect_ratio: WIDTH as f32 / HEIGHT as f32,
+	};
+	info.timing = RetroSystemTiming {
+		fps: FPS,
+		sample_rate: SAMPLE_RATE as f64,
+	};
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(environment: RetroEnvironment)
+{
+	Options::register(environment);
+	callbacks().environment = Some(environment);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(video_refresh: RetroVideoRefresh)
+{
+	callbacks().video_refresh = Some(video_refresh);
+}
+
+/// Unused, audio is sent a frame at a time through the batch callback
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: RetroAudioSample) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(audio_batch: RetroAudioSampleBatch)
+{
+	callbacks().audio_batch = Some(audio_batch);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(input_poll: RetroInputPoll)
+{
+	callbacks().input_poll = Some(input_poll);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(input_state: RetroInputState)
+{
+	callbacks().input_state = Some(input_state);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: u32, _device: u32) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset()
+{
+	if let Some(core) = core().as_mut()
+	{
+		core.reset();
+	}
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run()
+{
+	let callbacks = callbacks();
+	if let Some(core) = core().as_mut()
+	{
+		core.run(&callbacks);
+	}
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize
+{
+	core().as_ref().map_or(STATE_SIZE, |core| core.cpu.state_size())
+}
+
+/// # Safety
+/// `data` must point to `size` writable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool
+{
+	let out = unsafe { slice::from_raw_parts_mut(data as *mut u8, size) };
+	core().as_ref().is_some_and(|core| core.cpu.save_state(out).is_ok())
+}
+
+/// # Safety
+/// `data` must point to `size` readable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool
+{
+	let data = unsafe { slice::from_raw_parts(data as *const u8, size) };
+	core().as_mut().is_some_and(|core| core.cpu.load_state(data).is_ok())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_set(_index: u32, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+/// `game` must be null or point to a `retro_game_info` whose data is `size` readable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool
+{
+	let Some(game) = (unsafe { game.as_ref() })
+	else
+	{
+		return false;
+	};
+	if game.data.is_null()
+	{
+		return false;
+	}
+	let rom = unsafe { slice::from_raw_parts(game.data as *const u8, game.size) }.to_vec();
+
+	let environment = callbacks().environment;
+	let options = match environment
+	{
+		Some(environment) =>
+		{
+			let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+			// SAFETY: the frontend reads a single enum value
+			if !unsafe {
+				environment(
+					RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+					&mut format as *mut u32 as *mut c_void,
+				)
+			}
+			{
+				return false;
+			}
+			Options::read(environment)
+		}
+		None => Options::default(),
+	};
+	match Core::new(rom, options)
+	{
+		Ok(loaded) =>
+		{
+			*core() = Some(loaded);
+			true
+		}
+		Err(_) => false,
+	}
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(_game_type: u32, _info: *const RetroGameInfo, _num_info: usize) -> bool
+{
+	false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game()
+{
+	*core() = None;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> u32
+{
+	RETRO_REGION_NTSC
+}
+
+/// Exposes RAM so frontends can show it in their memory viewers and use RetroAchievements style cheats
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(id: u32) -> *mut c_void
+{
+	match core().as_mut()
+	{
+		Some(core) if id == RETRO_MEMORY_SYSTEM_RAM => core.cpu.ram.as_mut_ptr() as *mut c_void,
+		_ => std::ptr::null_mut(),
+	}
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(id: u32) -> usize
+{
+	match core().as_ref()
+	{
+		Some(core) if id == RETRO_MEMORY_SYSTEM_RAM => core.cpu.memory().ram_size,
+		_ => 0,
+	}
+}
@@ -0,0 +1,130 @@
+use std::ffi::{CStr, c_char, c_void};
+
+use chip8_core::{MemoryProfile, Platform, Quirks, palette};
+
+use crate::ffi::{RETRO_ENVIRONMENT_GET_VARIABLE, RETRO_ENVIRONMENT_SET_VARIABLES, RetroEnvironment, RetroVariable};
+
+const PLATFORM: &CStr = c"chip8_platform";
+const SPEED: &CStr = c"chip8_speed";
+const PALETTE: &CStr = c"chip8_palette";
+const SPRITE_EDGES: &CStr = c"chip8_sprite_edges";
+const DISPLAY_WAIT: &CStr = c"chip8_display_wait";
+const MEMORY: &CStr = c"chip8_memory";
+
+/// Settings exposed to the frontend as core options, the first value listed is the default
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options
+{
+	pub platform: Platform,
+	pub quirks: Quirks,
+	pub memory: MemoryProfile,
+	pub ticks_per_frame: usize,
+	/// Background and foreground as XRGB8888
+	pub palette: [u32; 2],
+}
+
+impl Default for Options
+{
+	fn default() -> Self
+	{
+		Self {
+			platform: Platform::Chip8,
+			quirks: Quirks::for_platform(Platform::Chip8),
+			memory: MemoryProfile::STANDARD,
+			ticks_per_frame: 30,
+			palette: xrgb(palette::PRESETS[0].1),
+		}
+	}
+}
+
+impl Options
+{
+	/// Declares the options, called from `retro_set_environment`
+	pub fn register(environment: RetroEnvironment)
+	{
+		let variables = [
+			RetroVariable {
+				key: PLATFORM.as_ptr(),
+				value: c"Quirk preset; chip8|schip".as_ptr(),
+			},
+			RetroVariable {
+				key: SPEED.as_ptr(),
+				value: c"Instructions per frame; 30|10|15|20|50|100|200|500|1000".as_ptr(),
+			},
+			RetroVariable {
+				key: PALETTE.as_ptr(),
+				value: c"Palette; default|mono|amber|green".as_ptr(),
+			},
+			RetroVariable {
+				key: SPRITE_EDGES.as_ptr(),
+				value: c"Sprites at screen edges; clip|wrap".as_ptr(),
+			},
+			RetroVariable {
+				key: DISPLAY_WAIT.as_ptr(),
+				value: c"Wait for the next frame after drawing; enabled|disabled".as_ptr(),
+			},
+			RetroVariable {
+				key: MEMORY.as_ptr(),
+				value: c"Memory (restarts the game); standard|modern|vip|eti660|xochip".as_ptr(),
+			},
+			RetroVariable {
+				key: std::ptr::null(),
+				value: std::ptr::null(),
+			},
+		];
+		// SAFETY: the frontend copies the variables before returning
+		unsafe { environment(RETRO_ENVIRONMENT_SET_VARIABLES, variables.as_ptr() as *mut c_void) };
+	}
+
+	/// Reads the current values, keeping the default for anything the frontend doesn't report
+	pub fn read(environment: RetroEnvironment) -> Self
+	{
+		let mut options = Self::default();
+		if let Some(platform) = get(environment, PLATFORM).and_then(|value| value.parse().ok())
+		{
+			options.platform = platform;
+			options.quirks = Quirks::for_platform(platform);
+		}
+		if let Some(speed) = get(environment, SPEED).and_then(|value| value.parse().ok())
+		{
+			options.ticks_per_frame = speed;
+		}
+		if let Some((_, colors)) =
+			get(environment, PALETTE).and_then(|value| palette::PRESETS.iter().find(|(name, _)| *name == value))
+		{
+			options.palette = xrgb(*colors);
+		}
+		options.quirks.wrap_sprites = get(environment, SPRITE_EDGES).as_deref() == Some("wrap");
+		if let Some(memory) = get(environment, MEMORY).and_then(|value| value.parse().ok())
+		{
+			options.memory = memory;
+		}
+		if get(environment, DISPLAY_WAIT).as_deref() == Some("disabled")
+		{
+			options.quirks.display_wait = false;
+		}
+		options
+	}
+}
+
+fn get(environment: RetroEnvironment, key: &CStr) -> Option<String>
+{
+	let mut variable = RetroVariable {
+		key: key.as_ptr(),
+		value: std::ptr::null(),
+	};
+	// SAFETY: the frontend fills in `value` with a string that stays valid until the next environment call
+	let found = unsafe { environment(RETRO_ENVIRONMENT_GET_VARIABLE, &mut variable as *mut _ as *mut c_void) };
+	if !found || variable.value.is_null()
+	{
+		return None;
+	}
+	let value = unsafe { CStr::from_ptr(variable.value as *const c_char) };
+	value.to_str().ok().map(String::from)
+}
+
+/// Packs background and foreground colors as XRGB8888
+fn xrgb(colors: [[u8; 3]; 2]) -> [u32; 2]
+{
+	colors.map(|[r, g, b]| u32::from_be_bytes([0, r, g, b]))
+}
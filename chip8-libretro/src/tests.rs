@@ -0,0 +1,103 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests
+{
+	use std::{
+		ffi::c_void,
+		ptr,
+		sync::{
+			Mutex,
+			atomic::{AtomicUsize, Ordering},
+		},
+	};
+
+	use crate::*;
+
+	/// The core is a global, so tests take turns with it
+	static SERIAL: Mutex<()> = Mutex::new(());
+	static LIT_PIXELS: AtomicUsize = AtomicUsize::new(0);
+	static AUDIO_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+	unsafe extern "C" fn environment(_cmd: u32, _data: *mut c_void) -> bool
+	{
+		true
+	}
+
+	unsafe extern "C" fn video_refresh(data: *const c_void, width: u32, height: u32, pitch: usize)
+	{
+		assert_eq!((width, height, pitch), (128, 64, 512));
+		let pixels = unsafe { slice::from_raw_parts(data as *const u32, 128 * 64) };
+		let background = pixels[128 * 64 - 1];
+		LIT_PIXELS.store(pixels.iter().filter(|p| **p != background).count(), Ordering::SeqCst);
+	}
+
+	unsafe extern "C" fn audio_batch(_data: *const i16, frames: usize) -> usize
+	{
+		AUDIO_FRAMES.fetch_add(frames, Ordering::SeqCst);
+		frames
+	}
+
+	unsafe extern "C" fn input_state(_port: u32, _device: u32, _index: u32, _id: u32) -> i16
+	{
+		0
+	}
+
+	fn load(rom: &[u8]) -> bool
+	{
+		retro_set_environment(environment);
+		retro_set_video_refresh(video_refresh);
+		retro_set_audio_sample_batch(audio_batch);
+		retro_set_input_state(input_state);
+		retro_init();
+		let game = RetroGameInfo {
+			path: ptr::null(),
+			data: rom.as_ptr() as *const c_void,
+			size: rom.len(),
+			meta: ptr::null(),
+		};
+		unsafe { retro_load_game(&game) }
+	}
+
+	#[test]
+	fn run_draws_and_beeps()
+	{
+		let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+		// Draws the 0 digit and starts the sound timer
+		let rom = [0xA0, 0x00, 0xD0, 0x05, 0x60, 0x10, 0xF0, 0x18, 0x12, 0x08];
+		assert!(load(&rom));
+		AUDIO_FRAMES.store(0, Ordering::SeqCst);
+		for _ in 0..3
+		{
+			retro_run();
+		}
+		// 14 pixels of the 0 digit, doubled in both directions
+		assert_eq!(LIT_PIXELS.load(Ordering::SeqCst), 14 * 4);
+		assert_eq!(AUDIO_FRAMES.load(Ordering::SeqCst), 735 * 3);
+		retro_deinit();
+	}
+
+	#[test]
+	fn serialize_round_trip()
+	{
+		let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+		let rom = [0x70, 0x01, 0x12, 0x00];
+		assert!(load(&rom));
+		retro_run();
+		let mut state = vec![0_u8; retro_serialize_size()];
+		assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+		let saved = core().as_ref().unwrap().cpu.registers[0];
+
+		retro_run();
+		assert_ne!(core().as_ref().unwrap().cpu.registers[0], saved);
+		assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, state.len()) });
+		assert_eq!(core().as_ref().unwrap().cpu.registers[0], saved);
+		retro_deinit();
+	}
+
+	#[test]
+	fn rejects_oversized_rom()
+	{
+		let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+		assert!(!load(&[0; 4096]));
+	}
+}
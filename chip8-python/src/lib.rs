@@ -0,0 +1,298 @@
+//! Python bindings for `chip8-core`, built as the `chip8` extension module with maturin
+#[cfg(test)]
+mod tests;
+
+use chip8_core::{
+	BlockCache, MemoryProfile, Platform, Quirks,
+	chip8::{DISPLAY_HEIGHT_HIGHRES, DISPLAY_WIDTH_HIGHRES},
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+const WIDTH: usize = DISPLAY_WIDTH_HIGHRES;
+const HEIGHT: usize = DISPLAY_HEIGHT_HIGHRES;
+
+/// A CHIP-8 machine stepped a frame at a time, with no clock of its own
+#[pyclass(name = "Chip8", module = "chip8")]
+pub struct Chip8
+{
+	cpu: chip8_core::Chip8,
+	/// Frames run through the block cache, which has to forget code whenever RAM is changed from outside
+	cache: Box<BlockCache>,
+	#[pyo3(get, set)]
+	ticks_per_frame: usize,
+}
+
+#[pymethods]
+impl Chip8
+{
+	#[new]
+	#[pyo3(signature = (platform = "chip8", ticks_per_frame = 30, seed = None, memory = "standard"))]
+	fn new(platform: &str, ticks_per_frame: usize, seed: Option<u64>, memory: &str) -> PyResult<Self>
+	{
+		let platform: Platform = platform.parse().map_err(PyValueError::new_err)?;
+		let memory: MemoryProfile = memory.parse().map_err(PyValueError::new_err)?;
+		let mut cpu = chip8_core::Chip8::try_with_memory(memory).map_err(PyValueError::new_err)?;
+		cpu.quirks = Quirks::for_platform(platform);
+		if let Some(seed) = seed
+		{
+			cpu.seed_rng(seed);
+		}
+		Ok(Self {
+			cpu,
+			cache: Box::default(),
+			ticks_per_frame,
+		})
+	}
+
+	/// Copies `rom` to the memory profile's start address and jumps to it
+	fn load_rom(&mut self, rom: &[u8]) -> PyResult<()>
+	{
+		self.cpu.try_load_code(rom).map_err(PyValueError::new_err)?;
+		self.cache.invalidate();
+		Ok(())
+	}
+
+	/// Runs `frames` 60Hz frames of `ticks_per_frame` instructions each
+	#[pyo3(signature = (frames = 1))]
+	fn step_frame(&mut self, frames: usize)
+	{
+		for _ in 0..frames
+		{
+			self.cache.run_frame(&mut self.cpu, self.ticks_per_frame);
+		}
+	}
+
+	/// Executes a single instruction without touching the timers
+	fn step(&mut self)
+	{
+		self.cache.step(&mut self.cpu);
+	}
+
+	fn set_key(&mut self, key: usize, pressed: bool) -> PyResult<()>
+	{
+		if key >= self.cpu.keys.len()
+		{
+			return Err(PyValueError::new_err(format!("key {key:#x} is not on the keypad")));
+		}
+		self.cpu.set_key(key, pressed);
+		Ok(())
+	}
+
+	/// Sets the whole keypad from a bitmask, bit n holding key n
+	fn set_keys(&mut self, mask: u16)
+	{
+		for (key, pressed) in self.cpu.keys.iter_mut().enumerate()
+		{
+			*pressed = mask >> key & 1 == 1;
+		}
+	}
+
+	#[getter]
+	fn keys(&self) -> u16
+	{
+		self.cpu
+			.keys
+			.iter()
+			.rev()
+			.fold(0, |mask, pressed| mask << 1 | *pressed as u16)
+	}
+
+	/// One byte per pixel, 0 or 1, row major at 128x64 with low resolution pixels doubled.
+	/// `numpy.frombuffer(chip.display(), numpy.uint8).reshape(chip8.DISPLAY_SHAPE)` gives an image.
+	fn display<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>>
+	{
+		let display = &self.cpu.display;
+		let scale = WIDTH / display.width();
+		PyBytes::new_with(py, WIDTH * HEIGHT, |pixels| {
+			for (i, pixel) in pixels.iter_mut().enumerate()
+			{
+				*pixel = display.pixel(i % WIDTH / scale, i / WIDTH / scale);
+			}
+			Ok(())
+		})
+	}
+
+	fn read_ram<'py>(&self, py: Python<'py>, address: usize, length: usize) -> PyResult<Bound<'py, PyBytes>>
+	{
+		self.check_range(address, length)?;
+		Ok(PyBytes::new(py, &self.cpu.ram[address..address + length]))
+	}
+
+	fn write_ram(&mut self, address: usize, data: &[u8]) -> PyResult<()>
+	{
+		self.check_range(address, data.len())?;
+		self.cpu.ram[address..address + data.len()].copy_from_slice(data);
+		self.cache.invalidate_range(address, data.len());
+		Ok(())
+	}
+
+	/// V0-VF
+	#[getter]
+	fn v(&self) -> [u8; 16]
+	{
+		self.cpu.registers
+	}
+
+	#[setter]
+	fn set_v(&mut self, registers: [u8; 16])
+	{
+		self.cpu.registers = registers;
+	}
+
+	#[getter]
+	fn i(&self) -> u16
+	{
+		self.cpu.reg_i
+	}
+
+	#[setter]
+	fn set_i(&mut self, value: u16)
+	{
+		self.cpu.reg_i = value;
+	}
+
+	#[getter]
+	fn pc(&self) -> usize
+	{
+		self.cpu.program_counter
+	}
+
+	#[setter]
+	fn set_pc(&mut self, value: usize) -> PyResult<()>
+	{
+		self.check_range(value, 0)?;
+		self.cpu.program_counter = value;
+		Ok(())
+	}
+
+	#[getter]
+	fn dt(&self) -> u8
+	{
+		self.cpu.reg_dt
+	}
+
+	#[setter]
+	fn set_dt(&mut self, value: u8)
+	{
+		self.cpu.reg_dt = value;
+	}
+
+	#[getter]
+	fn st(&self) -> u8
+	{
+		self.cpu.reg_st
+	}
+
+	#[setter]
+	fn set_st(&mut self, value: u8)
+	{
+		self.cpu.reg_st = value;
+	}
+
+	/// The return addresses currently on the stack, innermost last
+	#[getter]
+	fn stack(&self) -> Vec<usize>
+	{
+		// CALL pushes its own address, execution resumes at the instruction after it
+		self.cpu.stack[1..=self.cpu.stack_pointer]
+			.iter()
+			.map(|entry| *entry as usize + 2)
+			.collect()
+	}
+
+	#[getter]
+	fn halted(&self) -> bool
+	{
+		self.cpu.is_halted
+	}
+
+	#[getter]
+	fn high_res(&self) -> bool
+	{
+		self.cpu.high_res()
+	}
+
+	/// Whether DXYN wraps sprites around the screen edges instead of clipping them
+	#[getter]
+	fn wrap_sprites(&self) -> bool
+	{
+		self.cpu.quirks.wrap_sprites
+	}
+
+	#[setter]
+	fn set_wrap_sprites(&mut self, wrap: bool)
+	{
+		self.cpu.quirks.wrap_sprites = wrap;
+	}
+
+	/// Whether DXYN stops the frame until the next one, as on the VIP
+	#[getter]
+	fn display_wait(&self) -> bool
+	{
+		self.cpu.quirks.display_wait
+	}
+
+	#[setter]
+	fn set_display_wait(&mut self, wait: bool)
+	{
+		self.cpu.quirks.display_wait = wait;
+	}
+
+	/// Whether the sound timer is running, i.e. the buzzer should be heard
+	#[getter]
+	fn beeping(&self) -> bool
+	{
+		self.cpu.reg_st > 0
+	}
+
+	/// Bytes of RAM in the memory profile, which `read_ram` and `write_ram` stay within
+	#[getter]
+	fn ram_size(&self) -> usize
+	{
+		self.cpu.memory().ram_size
+	}
+
+	/// Length of the states `save_state` returns, which grows with the RAM size
+	#[getter]
+	fn state_size(&self) -> usize
+	{
+		self.cpu.state_size()
+	}
+
+	fn save_state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>>
+	{
+		PyBytes::new_with(py, self.cpu.state_size(), |out| {
+			self.cpu.save_state(out).map_err(PyValueError::new_err)
+		})
+	}
+
+	fn load_state(&mut self, state: &[u8]) -> PyResult<()>
+	{
+		self.cpu.load_state(state).map_err(PyValueError::new_err)?;
+		self.cache.invalidate();
+		Ok(())
+	}
+}
+
+impl Chip8
+{
+	fn check_range(&self, address: usize, length: usize) -> PyResult<()>
+	{
+		let ram_size = self.cpu.memory().ram_size;
+		match address.checked_add(length)
+		{
+			Some(end) if end <= ram_size => Ok(()),
+			_ => Err(PyValueError::new_err(format!(
+				"{length} bytes at {address:#05x} do not fit in {ram_size} bytes of RAM"
+			))),
+		}
+	}
+}
+
+#[pymodule]
+fn chip8(m: &Bound<'_, PyModule>) -> PyResult<()>
+{
+	m.add_class::<Chip8>()?;
+	m.add("DISPLAY_SHAPE", (HEIGHT, WIDTH))?;
+	Ok(())
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests
+{
+	use std::ffi::CStr;
+
+	use pyo3::{prelude::*, types::PyDict};
+
+	/// Runs `code` with the module imported as `chip8`
+	fn run(code: &CStr)
+	{
+		Python::initialize();
+		Python::attach(|py| {
+			let module = PyModule::new(py, "chip8").unwrap();
+			crate::chip8(&module).unwrap();
+			let globals = PyDict::new(py);
+			globals.set_item("chip8", module).unwrap();
+			py.run(code, Some(&globals), None).unwrap();
+		});
+	}
+
+	#[test]
+	fn drives_a_rom()
+	{
+		// Draws the sprite for the key held down, then loops
+		run(cr#"
+chip = chip8.Chip8(ticks_per_frame=20)
+chip.load_rom(bytes([0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x00]))
+chip.step_frame(5)
+assert sum(chip.display()) == 0 and chip.pc == 0x200
+
+chip.set_keys(1 << 0xA)
+assert chip.keys == 1 << 0xA
+chip.step_frame(5)
+display = chip.display()
+assert len(display) == chip8.DISPLAY_SHAPE[0] * chip8.DISPLAY_SHAPE[1]
+assert set(display) == {0, 1} and chip.v[0] == 0xA
+assert chip.i == 0xA * 5 and chip.read_ram(chip.i, 5) == bytes([0xF0, 0x90, 0xF0, 0x90, 0x90])
+"#);
+	}
+
+	#[test]
+	fn stack_holds_return_addresses()
+	{
+		// Calls 0x204, which calls 0x208, which loops
+		run(cr#"
+chip = chip8.Chip8()
+chip.load_rom(bytes([0x22, 0x04, 0x12, 0x02, 0x22, 0x08, 0x12, 0x06, 0x12, 0x08]))
+assert chip.stack == []
+chip.step()
+assert chip.stack == [0x202] and chip.pc == 0x204
+chip.step_frame()
+assert chip.stack == [0x202, 0x206] and chip.pc == 0x208
+"#);
+	}
+
+	#[test]
+	fn registers_and_ram_are_writable()
+	{
+		run(cr#"
+chip = chip8.Chip8("schip")
+chip.write_ram(0x300, b"\x12\x34")
+assert chip.read_ram(0x2FF, 4) == b"\x00\x12\x34\x00"
+chip.v = list(range(16))
+chip.i, chip.dt, chip.st = 0x300, 3, 2
+assert chip.v[15] == 15 and chip.beeping
+chip.step_frame(2)
+assert (chip.dt, chip.st) == (1, 0)
+assert not chip.wrap_sprites
+chip.wrap_sprites = True
+assert chip.wrap_sprites
+assert chip.display_wait
+chip.display_wait = False
+assert not chip.display_wait
+
+for bad in (lambda: chip.read_ram(0xFFF, 2), lambda: chip.set_key(16, True), lambda: chip8.Chip8("vip")):
+	try:
+		bad()
+		raise AssertionError("accepted out of range input")
+	except ValueError:
+		pass
+"#);
+	}
+
+	#[test]
+	fn save_state_round_trip()
+	{
+		run(cr#"
+chip = chip8.Chip8(seed=7)
+chip.load_rom(bytes([0xC0, 0xFF, 0x70, 0x01, 0x12, 0x00]))
+chip.step_frame(3)
+state = chip.save_state()
+assert len(state) == chip.state_size
+chip.step_frame(3)
+expected = (chip.v, chip.pc)
+chip.load_state(state)
+chip.step_frame(3)
+assert (chip.v, chip.pc) == expected
+
+try:
+	chip.load_state(state[:10])
+	raise AssertionError("accepted a truncated state")
+except ValueError:
+	pass
+"#);
+	}
+
+	#[test]
+	fn memory_profiles()
+	{
+		run(cr#"
+chip = chip8.Chip8(memory="eti660")
+chip.load_rom(bytes([0x16, 0x00]))
+assert chip.pc == 0x600 and chip.read_ram(0x600, 2) == b"\x16\x00"
+
+for memory, ram_size in (("standard", 0x1000), ("vip", 0x800), ("xochip", 0x10000)):
+	chip = chip8.Chip8(memory=memory)
+	assert chip.ram_size == ram_size
+	assert len(chip.save_state()) == chip.state_size > ram_size
+
+chip = chip8.Chip8(memory="vip")
+for bad in (lambda: chip.read_ram(0x800, 1), lambda: chip.load_rom(bytes(0x800)), lambda: chip8.Chip8(memory="2k")):
+	try:
+		bad()
+		raise AssertionError("accepted out of range input")
+	except ValueError:
+		pass
+"#);
+	}
+}
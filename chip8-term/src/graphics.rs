@@ -0,0 +1,296 @@
+use std::{env, fmt::Write};
+
+use chip8_core::{
+	Chip8,
+	chip8::{DISPLAY_HEIGHT_HIGHRES, DISPLAY_WIDTH_HIGHRES},
+};
+use crossterm::terminal;
+
+use crate::{palette::Palette, render::pixel};
+
+const WIDTH: usize = DISPLAY_WIDTH_HIGHRES;
+const HEIGHT: usize = DISPLAY_HEIGHT_HIGHRES;
+/// Used when the terminal doesn't report its size in pixels
+const FALLBACK_CELL: (usize, usize) = (8, 16);
+const FALLBACK_SCALE: usize = 4;
+/// Largest base64 payload the kitty protocol accepts per escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+/// Terminal graphics protocols able to show the display pixel for pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol
+{
+	Sixel,
+	Kitty,
+}
+
+/// Draws the display as an image of `scale` terminal pixels per high resolution pixel
+pub struct Graphics
+{
+	pub protocol: Protocol,
+	pub scale: usize,
+	cell: (usize, usize),
+}
+
+impl Graphics
+{
+	/// Uses `scale`, or the largest one leaving a row for the status line when left out
+	pub fn new(protocol: Protocol, scale: Option<usize>) -> Self
+	{
+		let size = terminal::window_size()
+			.ok()
+			.filter(|size| size.width > 0 && size.height > 0);
+		let cell = size.as_ref().map_or(FALLBACK_CELL, |size| {
+			(
+				(size.width / size.columns.max(1)) as usize,
+				(size.height / size.rows.max(1)) as usize,
+			)
+		});
+		let scale = scale.unwrap_or_else(|| {
+			size.map_or(FALLBACK_SCALE, |size| {
+				(size.width as usize / WIDTH).min((size.height as usize).saturating_sub(cell.1) / HEIGHT)
+			})
+		});
+		Self {
+			protocol,
+			scale: scale.max(1),
+			cell: (cell.0.max(1), cell.1.max(1)),
+		}
+	}
+
+	/// Terminal rows covered by the image
+	pub fn rows(&self) -> usize
+	{
+		(HEIGHT * self.scale).div_ceil(self.cell.1)
+	}
+
+	/// The escape sequences drawing the display at the cursor
+	pub fn encode(&self, cpu: &Chip8, palette: &Palette) -> String
+	{
+		let frame = framebuffer(cpu);
+		match self.protocol
+		{
+			Protocol::Sixel => sixel(&frame, palette, self.scale),
+			Protocol::Kitty => kitty(&frame, palette, self.scale),
+		}
+	}
+}
+
+/// Lit pixels at 128x64 with low resolution pixels doubled, the same image `render_image` produces
+pub fn framebuffer(cpu: &Chip8) -> Vec<bool>
+{
+	let scale = WIDTH / cpu.display.width();
+	(0..WIDTH * HEIGHT)
+		.map(|i| pixel(cpu, i % WIDTH / scale, i / WIDTH / scale))
+		.collect()
+}
+
+/// Encodes a 128x64 frame as a two color sixel image, scaled up by `scale`
+pub fn sixel(frame: &[bool], palette: &Palette, scale: usize) -> String
+{
+	let (width, height) = (WIDTH * scale, HEIGHT * scale);
+	let lit = |x: usize, y: usize| frame[y / scale * WIDTH + x / scale];
+	let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+	for (index, lit_color) in [false, true].into_iter().enumerate()
+	{
+		let [r, g, b] = palette.color(lit_color).map(|c| c as usize * 100 / 255);
+		let _ = write!(out, "#{};2;{};{};{}", index, r, g, b);
+	}
+	for band in (0..height).step_by(6)
+	{
+		for (index, lit_color) in [false, true].into_iter().enumerate()
+		{
+			let _ = write!(out, "#{}", index);
+			let mut run: Option<(u8, usize)> = None;
+			for x in 0..width
+			{
+				let mut bits = 0;
+				for dy in 0..6
+				{
+					if band + dy < height && lit(x, band + dy) == lit_color
+					{
+						bits |= 1 << dy;
+					}
+				}
+				let sixel = b'?' + bits;
+				run = match run
+				{
+					Some((c, n)) if c == sixel => Some((c, n + 1)),
+					Some(previous) =>
+					{
+						push_run(&mut out, previous);
+						Some((sixel, 1))
+					}
+					None => Some((sixel, 1)),
+				};
+			}
+			if let Some(run) = run
+			{
+				push_run(&mut out, run);
+			}
+			// Return to the start of the band for the next color
+			out.push('$');
+		}
+		out.push('-');
+	}
+	out.push_str("\x1b\\");
+	out
+}
+
+fn push_run(out: &mut String, (sixel, count): (u8, usize))
+{
+	if count > 3
+	{
+		let _ = write!(out, "!{}{}", count, sixel as char);
+	}
+	else
+	{
+		out.extend(std::iter::repeat_n(sixel as char, count));
+	}
+}
+
+/// Encodes a 128x64 frame as a kitty graphics image scaled up by `scale`, replacing the one drawn before it
+pub fn kitty(frame: &[bool], palette: &Palette, scale: usize) -> String
+{
+	let (width, height) = (WIDTH * scale, HEIGHT * scale);
+	let mut rgb = Vec::with_capacity(width * height * 3);
+	for y in 0..height
+	{
+		for x in 0..width
+		{
+			rgb.extend_from_slice(&palette.color(frame[y / scale * WIDTH + x / scale]));
+		}
+	}
+	let payload = base64(&rgb);
+	let chunks: Vec<_> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+	let mut out = String::with_capacity(payload.len() + chunks.len() * 16 + 64);
+	for (i, chunk) in chunks.iter().enumerate()
+	{
+		let more = (i + 1 < chunks.len()) as u8;
+		let chunk = std::str::from_utf8(chunk).expect("base64 is ascii");
+		if i == 0
+		{
+			// Same image and placement ids every frame so the terminal replaces the image in place
+			let _ = write!(
+				out,
+				"\x1b_Ga=T,i=1,p=1,f=24,s={},v={},q=2,C=1,m={};{}\x1b\\",
+				width, height, more, chunk
+			);
+		}
+		else
+		{
+			let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+		}
+	}
+	out
+}
+
+fn base64(data: &[u8]) -> String
+{
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+	for chunk in data.chunks(3)
+	{
+		let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+		let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+		for i in 0..4
+		{
+			if i <= chunk.len()
+			{
+				out.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3F) as usize] as char);
+			}
+			else
+			{
+				out.push('=');
+			}
+		}
+	}
+	out
+}
+
+/// Works out which protocol the terminal supports, must be called in raw mode before reading any events.
+/// Kitty sets environment variables locally, over SSH the terminal has to be asked.
+pub fn detect() -> Option<Protocol>
+{
+	if env::var_os("KITTY_WINDOW_ID").is_some() || env::var("TERM").is_ok_and(|term| term == "xterm-kitty")
+	{
+		return Some(Protocol::Kitty);
+	}
+	#[cfg(unix)]
+	{
+		query()
+	}
+	#[cfg(not(unix))]
+	{
+		None
+	}
+}
+
+/// Sends a kitty graphics query followed by a primary device attributes request, which every terminal answers
+#[cfg(unix)]
+fn query() -> Option<Protocol>
+{
+	use std::{
+		io::{self, IsTerminal, Write},
+		time::{Duration, Instant},
+	};
+
+	use rustix::event::{PollFd, PollFlags, Timespec, poll};
+
+	let stdin = io::stdin();
+	if !stdin.is_terminal()
+	{
+		return None;
+	}
+	let mut stdout = io::stdout();
+	stdout
+		.write_all(b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c")
+		.ok()?;
+	stdout.flush().ok()?;
+
+	let deadline = Instant::now() + Duration::from_secs(1);
+	let mut reply = Vec::new();
+	let mut buf = [0; 256];
+	// Read straight from the descriptor so nothing typed afterwards gets buffered away from crossterm
+	while device_attributes(&String::from_utf8_lossy(&reply)).is_none()
+	{
+		let left = deadline.saturating_duration_since(Instant::now());
+		let timeout = Timespec {
+			tv_sec: left.as_secs() as i64,
+			tv_nsec: left.subsec_nanos() as _,
+		};
+		let mut fds = [PollFd::new(&stdin, PollFlags::IN)];
+		if left.is_zero() || poll(&mut fds, Some(&timeout)).ok()? == 0
+		{
+			return None;
+		}
+		let read = rustix::io::read(&stdin, &mut buf).ok()?;
+		reply.extend_from_slice(&buf[..read]);
+	}
+	protocol_from_reply(&String::from_utf8_lossy(&reply))
+}
+
+/// Parameters of the primary device attributes reply, `ESC [ ? 62 ; 4 c` gives `62;4`
+fn device_attributes(reply: &str) -> Option<&str>
+{
+	let start = reply.find("\x1b[?")? + 3;
+	let end = start + reply[start..].find('c')?;
+	Some(&reply[start..end])
+}
+
+/// Kitty answers the graphics query, sixel terminals list attribute 4
+pub fn protocol_from_reply(reply: &str) -> Option<Protocol>
+{
+	if reply.contains("\x1b_Gi=31;OK")
+	{
+		Some(Protocol::Kitty)
+	}
+	else if device_attributes(reply)?.split(';').any(|attribute| attribute == "4")
+	{
+		Some(Protocol::Sixel)
+	}
+	else
+	{
+		None
+	}
+}
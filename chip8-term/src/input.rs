@@ -0,0 +1,86 @@
+use chip8_core::Chip8;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+
+/// The usual 1234/QWER/ASDF/ZXCV keyboard layout
+const KEYBOARD_LAYOUT: [(char, usize); 16] = [
+	('1', 0x1),
+	('2', 0x2),
+	('3', 0x3),
+	('4', 0xC),
+	('q', 0x4),
+	('w', 0x5),
+	('e', 0x6),
+	('r', 0xD),
+	('a', 0x7),
+	('s', 0x8),
+	('d', 0x9),
+	('f', 0xE),
+	('z', 0xA),
+	('x', 0x0),
+	('c', 0xB),
+	('v', 0xF),
+];
+
+/// Frames a key stays down after a press when the terminal can't report releases, long enough to bridge the
+/// autorepeat delay of most systems
+const PRESS_HOLD: u32 = 30;
+/// Frames a key stays down after each autorepeat, a little longer than the usual repeat interval
+const REPEAT_HOLD: u32 = 6;
+
+/// Keypad state built from terminal key events.
+/// Most terminals only report presses, so without release events keys are held for a while after each press or
+/// autorepeat instead.
+pub struct Keypad
+{
+	reports_releases: bool,
+	/// Frames each key stays down for, `u32::MAX` until released when releases are reported
+	held: [u32; 16],
+}
+
+impl Keypad
+{
+	pub fn new(reports_releases: bool) -> Self
+	{
+		Self {
+			reports_releases,
+			held: [0; 16],
+		}
+	}
+
+	/// Updates the keypad from an event, returning whether the key is on it
+	pub fn handle(&mut self, event: &KeyEvent) -> bool
+	{
+		let KeyCode::Char(c) = event.code
+		else
+		{
+			return false;
+		};
+		let Some(&(_, key)) = KEYBOARD_LAYOUT.iter().find(|(k, _)| *k == c.to_ascii_lowercase())
+		else
+		{
+			return false;
+		};
+		let held = &mut self.held[key];
+		*held = match (event.kind, self.reports_releases)
+		{
+			(KeyEventKind::Release, _) => 0,
+			(_, true) => u32::MAX,
+			(_, false) if *held > 0 => (*held).max(REPEAT_HOLD),
+			(_, false) => PRESS_HOLD,
+		};
+		true
+	}
+
+	/// Copies the keypad to the machine and counts down keys held by timeout, called once per frame
+	pub fn frame(&mut self, cpu: &mut Chip8)
+	{
+		for (key, held) in self.held.iter_mut().enumerate()
+		{
+			cpu.set_key(key, *held > 0);
+			if *held != u32::MAX
+			{
+				*held = held.saturating_sub(1);
+			}
+		}
+	}
+}
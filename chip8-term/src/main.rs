@@ -0,0 +1,282 @@
+//! A terminal frontend for `chip8-core`, for running ROMs over SSH or without a graphical session
+mod graphics;
+mod input;
+mod palette;
+mod render;
+#[cfg(test)]
+mod tests;
+
+use std::{
+	fs,
+	io::{self, Write},
+	path::PathBuf,
+	time::{Duration, Instant},
+};
+
+use chip8_core::{Chip8, MemoryProfile, Platform, Quirks, memory::MAX_MEMORY};
+use clap::{Parser, ValueEnum};
+use crossterm::{
+	cursor::{Hide, MoveTo, Show},
+	event::{
+		self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+		PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+	},
+	execute, queue,
+	style::Print,
+	terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{
+	graphics::{Graphics, Protocol},
+	input::Keypad,
+	palette::Palette,
+	render::{Renderer, TextMode, resolution},
+};
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+#[derive(Parser, Debug)]
+#[command(version, about = "A CHIP-8 and SUPER-CHIP emulator for the terminal")]
+struct Args
+{
+	/// ROM file to load
+	rom: PathBuf,
+
+	/// Platform whose quirks to emulate [chip8, schip]
+	#[arg(short, long, default_value = "chip8")]
+	platform: Platform,
+
+	/// Wrap sprites around the screen edges instead of clipping them [default: false]
+	#[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+	wrap_sprites: Option<bool>,
+
+	/// Wait for the next frame after DXYN, defaults to the platform's behaviour
+	#[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+	display_wait: Option<bool>,
+
+	/// Instructions executed per 60Hz frame
+	#[arg(short, long, default_value_t = 30)]
+	speed: usize,
+
+	/// RAM size and layout [standard, modern, vip, eti660, xochip]
+	#[arg(long, default_value = "standard")]
+	memory: MemoryProfile,
+
+	/// Address the ROM is loaded at and execution starts from, defaults to the memory profile's
+	#[arg(long, value_parser = parse_address)]
+	start_address: Option<usize>,
+
+	/// Seed for the random number generator used by CXNN
+	#[arg(long)]
+	seed: Option<u64>,
+
+	/// How to draw the display, auto picks a graphics protocol when the terminal supports one
+	#[arg(short, long, value_enum, default_value_t = Mode::Auto)]
+	mode: Mode,
+
+	/// Palette preset [default, mono, amber, green] or a BACKGROUND,FOREGROUND hex pair
+	#[arg(long, default_value = "default")]
+	palette: Palette,
+
+	/// Terminal pixels per high resolution pixel in sixel and kitty modes, defaults to filling the terminal
+	#[arg(long, value_parser = clap::value_parser!(u16).range(1..=32))]
+	scale: Option<u16>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Mode
+{
+	Auto,
+	/// Text with half block characters
+	HalfBlock,
+	/// Text with braille dots, a quarter of the size
+	Braille,
+	/// Sixel images, supported by xterm -ti vt340, foot, WezTerm, mlterm and others
+	Sixel,
+	/// The kitty graphics protocol, supported by kitty, WezTerm, Ghostty and Konsole
+	Kitty,
+}
+
+/// Puts the terminal into raw mode on the alternate screen, restoring it when dropped
+struct Terminal
+{
+	enhanced_keyboard: bool,
+	/// The graphics protocol found when asked to look for one
+	graphics: Option<Protocol>,
+}
+
+impl Terminal
+{
+	fn enter(detect_graphics: bool) -> io::Result<Self>
+	{
+		terminal::enable_raw_mode()?;
+		// Both queries read replies from stdin, so they go before anything else does
+		let graphics = if detect_graphics { graphics::detect() } else { None };
+		// Terminals implementing the kitty keyboard protocol can tell us when keys are released
+		let enhanced_keyboard = terminal::supports_keyboard_enhancement().unwrap_or(false);
+		let mut stdout = io::stdout();
+		execute!(stdout, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+		if enhanced_keyboard
+		{
+			execute!(
+				stdout,
+				PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+			)?;
+		}
+		Ok(Self {
+			enhanced_keyboard,
+			graphics,
+		})
+	}
+}
+
+impl Drop for Terminal
+{
+	fn drop(&mut self)
+	{
+		let mut stdout = io::stdout();
+		if self.graphics == Some(Protocol::Kitty)
+		{
+			// Kitty images outlive the alternate screen unless deleted
+			let _ = execute!(stdout, Print("\x1b_Ga=d,d=A\x1b\\"));
+		}
+		if self.enhanced_keyboard
+		{
+			let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+		}
+		let _ = execute!(stdout, Show, LeaveAlternateScreen);
+		let _ = terminal::disable_raw_mode();
+	}
+}
+
+fn main() -> io::Result<()>
+{
+	let args = Args::parse();
+	let rom = fs::read(&args.rom)?;
+	let memory = MemoryProfile {
+		start_address: args.start_address.unwrap_or(args.memory.start_address),
+		..args.memory
+	};
+	let mut cpu =
+		Chip8::try_with_memory(memory).map_err(|err| io::Error::other(format!("invalid memory profile: {}", err)))?;
+	let quirks = Quirks::for_platform(args.platform);
+	cpu.quirks = Quirks {
+		wrap_sprites: args.wrap_sprites.unwrap_or(quirks.wrap_sprites),
+		display_wait: args.display_wait.unwrap_or(quirks.display_wait),
+		..quirks
+	};
+	if let Some(seed) = args.seed
+	{
+		cpu.seed_rng(seed);
+	}
+	cpu.try_load_code(rom)
+		.map_err(|err| io::Error::other(format!("can't load {}: {}", args.rom.display(), err)))?;
+
+	let mut terminal = Terminal::enter(args.mode == Mode::Auto)?;
+	let scale = args.scale.map(usize::from);
+	let renderer = match (args.mode, terminal.graphics)
+	{
+		(Mode::Auto, Some(protocol)) => Renderer::Graphics(Graphics::new(protocol, scale)),
+		(Mode::Auto | Mode::HalfBlock, _) => Renderer::Text(TextMode::HalfBlock),
+		(Mode::Braille, _) => Renderer::Text(TextMode::Braille),
+		(Mode::Sixel, _) => Renderer::Graphics(Graphics::new(Protocol::Sixel, scale)),
+		(Mode::Kitty, _) =>
+		{
+			terminal.graphics = Some(Protocol::Kitty);
+			Renderer::Graphics(Graphics::new(Protocol::Kitty, scale))
+		}
+	};
+	run(&mut cpu, &args, &renderer, Keypad::new(terminal.enhanced_keyboard))
+}
+
+/// Runs frames at 60Hz until Esc or Ctrl+C, redrawing only when the display changes
+fn run(cpu: &mut Chip8, args: &Args, renderer: &Renderer, mut keypad: Keypad) -> io::Result<()>
+{
+	let mut stdout = io::stdout();
+	let mut next_frame = Instant::now();
+	let mut redraw = true;
+	let mut last_resolution = resolution(cpu);
+	let mut last_status = String::new();
+	let mut rows = 0;
+	loop
+	{
+		while event::poll(next_frame.saturating_duration_since(Instant::now()))?
+		{
+			match event::read()?
+			{
+				Event::Key(key) if is_quit(&key) => return Ok(()),
+				Event::Key(key) =>
+				{
+					keypad.handle(&key);
+				}
+				Event::Resize(..) => redraw = true,
+				_ => (),
+			}
+		}
+		keypad.frame(cpu);
+		cpu.run_frame(args.speed);
+
+		if resolution(cpu) != last_resolution
+		{
+			last_resolution = resolution(cpu);
+			queue!(stdout, Clear(ClearType::All))?;
+			redraw = true;
+		}
+		let status = status_line(cpu, args);
+		if cpu.need_draw || redraw
+		{
+			rows = renderer.draw(&mut stdout, cpu, &args.palette)?;
+		}
+		if cpu.need_draw || redraw || status != last_status
+		{
+			cpu.need_draw = false;
+			redraw = false;
+			queue!(
+				stdout,
+				MoveTo(0, rows as u16),
+				Clear(ClearType::CurrentLine),
+				Print(&status)
+			)?;
+			stdout.flush()?;
+			last_status = status;
+		}
+
+		// Skip frames we fell behind on rather than running them all at once
+		next_frame = (next_frame + FRAME).max(Instant::now());
+	}
+}
+
+fn is_quit(key: &KeyEvent) -> bool
+{
+	key.kind != KeyEventKind::Release
+		&& (key.code == KeyCode::Esc || key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+fn status_line(cpu: &Chip8, args: &Args) -> String
+{
+	let mut status = format!("{} at {} instructions/frame, Esc quits", args.platform, args.speed);
+	if cpu.reg_st > 0
+	{
+		status.push_str("  ♪");
+	}
+	if cpu.is_halted
+	{
+		status.push_str("  halted");
+	}
+	status
+}
+
+fn parse_address(value: &str) -> Result<usize, String>
+{
+	let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X"))
+	{
+		Some(hex) => usize::from_str_radix(hex, 16),
+		None => value.parse(),
+	};
+	match parsed
+	{
+		Ok(addr) if addr < MAX_MEMORY => Ok(addr),
+		Ok(_) => Err(format!("{} must be below {:#x}", value, MAX_MEMORY)),
+		Err(_) => Err(format!("'{}' is not a valid address", value)),
+	}
+}
@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+use chip8_core::palette;
+
+/// Background and foreground colors, from the presets shared with the other frontends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette
+{
+	pub background: [u8; 3],
+	pub foreground: [u8; 3],
+}
+
+impl Palette
+{
+	pub const fn from_rgb(background: [u8; 3], foreground: [u8; 3]) -> Self
+	{
+		Self { background, foreground }
+	}
+
+	/// The color for a pixel that is lit or not
+	pub fn color(&self, lit: bool) -> [u8; 3]
+	{
+		if lit { self.foreground } else { self.background }
+	}
+}
+
+impl Default for Palette
+{
+	fn default() -> Self
+	{
+		let [background, foreground] = palette::PRESETS[0].1;
+		Self::from_rgb(background, foreground)
+	}
+}
+
+impl FromStr for Palette
+{
+	type Err = String;
+
+	/// Accepts either a preset name or a `background,foreground` pair of hex colors
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		let [background, foreground] = palette::parse(s)?;
+		Ok(Self::from_rgb(background, foreground))
+	}
+}
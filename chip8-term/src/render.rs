@@ -0,0 +1,125 @@
+use std::io::{self, Write};
+
+use chip8_core::Chip8;
+use crossterm::{
+	cursor::MoveTo,
+	queue,
+	style::{Color, Colors, Print, ResetColor, SetColors},
+};
+
+use crate::{graphics::Graphics, palette::Palette};
+
+/// How display pixels are packed into character cells
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextMode
+{
+	/// 1x2 pixels per cell with ▀ and ▄, 128x32 cells at high resolution
+	HalfBlock,
+	/// 2x4 pixels per cell with braille dots, 64x16 cells at high resolution
+	Braille,
+}
+
+/// How the display is drawn in the terminal
+pub enum Renderer
+{
+	Text(TextMode),
+	Graphics(Graphics),
+}
+
+impl Renderer
+{
+	/// Draws the display from the top left corner, returning the rows it covers
+	pub fn draw(&self, out: &mut impl Write, cpu: &Chip8, palette: &Palette) -> io::Result<usize>
+	{
+		match self
+		{
+			Renderer::Text(mode) =>
+			{
+				let lines = render_text(cpu, *mode);
+				let [r, g, b] = palette.foreground;
+				let foreground = Color::Rgb { r, g, b };
+				let [r, g, b] = palette.background;
+				queue!(out, SetColors(Colors::new(foreground, Color::Rgb { r, g, b })))?;
+				for (row, line) in lines.iter().enumerate()
+				{
+					queue!(out, MoveTo(0, row as u16), Print(line))?;
+				}
+				queue!(out, ResetColor)?;
+				Ok(lines.len())
+			}
+			Renderer::Graphics(graphics) =>
+			{
+				queue!(out, MoveTo(0, 0), Print(graphics.encode(cpu, palette)))?;
+				Ok(graphics.rows())
+			}
+		}
+	}
+}
+
+/// Braille dot bits for each pixel of a 2x4 cell, indexed by [y][x]
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Width and height of the display at its current resolution
+pub fn resolution(cpu: &Chip8) -> (usize, usize)
+{
+	(cpu.display.width(), cpu.display.height())
+}
+
+/// Whether the pixel at native resolution coordinates is lit
+pub fn pixel(cpu: &Chip8, x: usize, y: usize) -> bool
+{
+	cpu.display.is_lit(x, y)
+}
+
+/// The display as lines of text at its native resolution
+pub fn render_text(cpu: &Chip8, mode: TextMode) -> Vec<String>
+{
+	let (width, height) = resolution(cpu);
+	match mode
+	{
+		TextMode::HalfBlock => (0..height)
+			.step_by(2)
+			.map(|y| {
+				(0..width)
+					.map(|x| match (pixel(cpu, x, y), pixel(cpu, x, y + 1))
+					{
+						(false, false) => ' ',
+						(true, false) => '▀',
+						(false, true) => '▄',
+						(true, true) => '█',
+					})
+					.collect()
+			})
+			.collect(),
+		TextMode::Braille => (0..height)
+			.step_by(4)
+			.map(|y| {
+				(0..width)
+					.step_by(2)
+					.map(|x| {
+						let mut dots = 0;
+						for (dy, row) in BRAILLE_DOTS.iter().enumerate()
+						{
+							for (dx, dot) in row.iter().enumerate()
+							{
+								if pixel(cpu, x + dx, y + dy)
+								{
+									dots |= dot;
+								}
+							}
+						}
+						// An empty braille cell still shows faint dots in some fonts
+						if dots == 0
+						{
+							' '
+						}
+						else
+						{
+							char::from_u32(0x2800 + dots).unwrap()
+						}
+					})
+					.collect()
+			})
+			.collect(),
+	}
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests
+{
+	use chip8_core::Chip8;
+	use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
+
+	use crate::{
+		graphics::{Protocol, framebuffer, kitty, protocol_from_reply, sixel},
+		input::Keypad,
+		palette::Palette,
+		render::{TextMode, render_text},
+	};
+
+	fn key(c: char, kind: KeyEventKind) -> KeyEvent
+	{
+		KeyEvent {
+			code: KeyCode::Char(c),
+			modifiers: KeyModifiers::NONE,
+			kind,
+			state: KeyEventState::NONE,
+		}
+	}
+
+	#[test]
+	fn text_modes_cover_the_display()
+	{
+		let mut cpu = Chip8::new();
+		// Top left pixel and the bottom right one at low resolution
+		cpu.display.set_pixel(0, 0, 1);
+		cpu.display.set_pixel(63, 31, 1);
+		let half = render_text(&cpu, TextMode::HalfBlock);
+		assert_eq!(half.len(), 16);
+		assert!(half.iter().all(|line| line.chars().count() == 64));
+		assert!(half[0].starts_with('▀'));
+		assert!(half[15].ends_with('▄'));
+
+		cpu.set_high_res(true);
+		cpu.display.set_pixel(0, 0, 1);
+		cpu.display.set_pixel(1, 0, 1);
+		cpu.display.set_pixel(127, 63, 1);
+		let braille = render_text(&cpu, TextMode::Braille);
+		assert_eq!(braille.len(), 16);
+		assert!(braille.iter().all(|line| line.chars().count() == 64));
+		assert!(braille[0].starts_with('⠉'));
+		assert!(braille[15].ends_with('⢀'));
+		assert_eq!(braille[8].trim(), "");
+	}
+
+	#[test]
+	fn keys_without_releases_are_held_for_a_while()
+	{
+		let mut cpu = Chip8::new();
+		let mut keypad = Keypad::new(false);
+		assert!(keypad.handle(&key('W', KeyEventKind::Press)));
+		assert!(!keypad.handle(&key('p', KeyEventKind::Press)));
+		let mut frames = 0;
+		loop
+		{
+			keypad.frame(&mut cpu);
+			if !cpu.keys[0x5]
+			{
+				break;
+			}
+			frames += 1;
+		}
+		assert!((10..60).contains(&frames), "held for {} frames", frames);
+
+		let mut keypad = Keypad::new(true);
+		keypad.handle(&key('v', KeyEventKind::Press));
+		for _ in 0..100
+		{
+			keypad.frame(&mut cpu);
+		}
+		assert!(cpu.keys[0xF]);
+		keypad.handle(&key('v', KeyEventKind::Release));
+		keypad.frame(&mut cpu);
+		assert!(!cpu.keys[0xF]);
+	}
+
+	#[test]
+	fn graphics_encode_the_framebuffer()
+	{
+		let mut cpu = Chip8::new();
+		cpu.display.set_pixel(0, 0, 1);
+		let frame = framebuffer(&cpu);
+		// Low resolution pixels are doubled
+		assert_eq!(frame.iter().filter(|lit| **lit).count(), 4);
+		assert!(frame[0] && frame[1] && frame[128] && frame[129]);
+
+		let palette: Palette = "000000,ffffff".parse().unwrap();
+		let image = sixel(&frame, &palette, 2);
+		assert!(image.starts_with("\x1bPq\"1;1;256;128#0;2;0;0;0#1;2;100;100;100"));
+		assert!(image.ends_with("-\x1b\\"));
+		// The first band has a 4x4 foreground block in the top left, the rest is background
+		assert!(image.contains("#0!4o!252~$#1!4N!252?$-#0!256~$#1!256?$-"));
+		assert_eq!(image.matches('-').count(), 22);
+
+		let image = kitty(&frame, &palette, 1);
+		assert!(image.starts_with("\x1b_Ga=T,i=1,p=1,f=24,s=128,v=64,q=2,C=1,m=1;////"));
+		// 24KiB of RGB is 32KiB of base64, sent in 4KiB chunks
+		assert_eq!(image.matches("\x1b_G").count(), 8);
+		assert!(image.contains("\x1b\\\x1b_Gm=0;AAAA") && image.ends_with("AAAA\x1b\\"));
+	}
+
+	#[test]
+	fn detects_protocols_from_replies()
+	{
+		assert_eq!(
+			protocol_from_reply("\x1b_Gi=31;OK\x1b\\\x1b[?62;22c"),
+			Some(Protocol::Kitty)
+		);
+		assert_eq!(protocol_from_reply("\x1b[?62;4;6;22c"), Some(Protocol::Sixel));
+		assert_eq!(protocol_from_reply("\x1b[?1;2c"), None);
+		assert_eq!(protocol_from_reply("\x1b[?64;44c"), None);
+		assert!("vip".parse::<Palette>().is_err());
+	}
+}
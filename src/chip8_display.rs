@@ -1,39 +1,173 @@
-use std::{env, fs};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
 
 use bevy::{asset::RenderAssetUsages, prelude::*};
-use image::ImageBuffer;
-use rayon::prelude::*;
+use chip8_core::chip8::Chip8;
 
-use crate::chip8::{Chip8, DISPLAY_HEIGHT_HIGHRES, DISPLAY_WIDTH, DISPLAY_WIDTH_HIGHRES};
+use crate::{
+	controls::{EmulatorState, Hotkeys, handle_hotkeys, update_title},
+	inspector::{Inspector, draw_inspector, inspector_input},
+	memory_viewer::{MemoryViewer, draw_memory_viewer, memory_viewer_input, track_writes},
+	movie::{Movie, MovieState, movie_input},
+	recorder::{Recorder, RecordingFormat, capture_frame, recorder_input},
+	render::{render_image, write_image},
+	rom_browser::{RomBrowser, browser_click, browser_input, draw_browser, file_drop},
+	rom_db::{RomDatabase, RomInfo},
+	screenshot::{ScreenshotScale, screenshot_input},
+	settings::{BaseSettings, Chip8Settings, KeyHints, Keymap},
+};
 
-const FPS: f32 = 60.;
-pub struct Chip8Plugin;
+const FPS: f64 = 60.;
+
+#[derive(Default)]
+pub struct Chip8Plugin
+{
+	pub rom: Option<Chip8Rom>,
+	/// Settings before any ROM database hints are applied
+	pub settings: Chip8Settings,
+	pub rom_db: RomDatabase,
+	pub keymap: Keymap,
+	pub hotkeys: Hotkeys,
+	/// Directory listed by the ROM browser
+	pub rom_dir: PathBuf,
+	/// Format of clips recorded with the record hotkey
+	pub record_format: RecordingFormat,
+	/// Output pixels per low resolution display pixel in scaled screenshots
+	pub screenshot_scale: u32,
+	/// Movie to play back from the start
+	pub movie: Option<Movie>,
+}
 
 #[derive(Resource)]
-pub struct Chip8CPU(pub Chip8, pub Timer);
+pub struct Chip8CPU(pub Chip8);
+
+/// The ROM currently loaded, kept around so the machine can be reset
+#[derive(Resource, Clone)]
+pub struct Chip8Rom
+{
+	pub path: String,
+	pub bytes: Vec<u8>,
+	/// The ROM database entry, if the ROM is known
+	pub info: Option<RomInfo>,
+	/// The machine as loaded, copied on every soft reset
+	pub machine: Chip8,
+}
+
+impl Chip8Rom
+{
+	/// Looks a ROM up in the database and loads it, returning it with the settings it should run with
+	pub fn open(
+		path: &Path,
+		bytes: Vec<u8>,
+		base: &Chip8Settings,
+		db: &RomDatabase,
+	) -> Result<(Self, Chip8Settings), String>
+	{
+		let info = db.lookup(&bytes);
+		let settings = base.for_rom(info.as_ref());
+		let machine = settings.try_create_cpu(&bytes).map_err(|err| {
+			format!(
+				"can't run {} with memory profile {}: {}",
+				path.display(),
+				settings.memory,
+				err
+			)
+		})?;
+		let rom = Self {
+			path: path.to_string_lossy().into_owned(),
+			bytes,
+			info,
+			machine,
+		};
+		Ok((rom, settings))
+	}
+}
+
+/// Requests replacing the running program with the ROM at the given path
+#[derive(Message)]
+pub struct LoadRom(pub PathBuf);
 
 impl Plugin for Chip8Plugin
 {
 	fn build(&self, app: &mut bevy::app::App)
 	{
-		let args: Vec<String> = env::args().collect();
-		if args.len() < 2
+		let mut browser = RomBrowser::new(self.rom_dir.clone());
+		let settings = match &self.rom
 		{
-			println!("No file provided");
-			return;
-		}
-		let path = &args[1];
-		let file = fs::read(path);
-
-		let bytes = file.expect("Failed to read file");
+			Some(rom) =>
+			{
+				let settings = self.settings.for_rom(rom.info.as_ref());
+				browser.add_recent(rom.path.as_ref());
+				app.insert_resource(Chip8CPU(rom.machine.clone()))
+					.insert_resource(KeyHints::from_info(rom.info.as_ref()))
+					.insert_resource(rom.clone());
+				settings
+			}
+			None =>
+			{
+				// Nothing to run until a ROM is picked or dropped onto the window
+				let mut cpu = Chip8::new();
+				cpu.is_halted = true;
+				app.insert_resource(Chip8CPU(cpu)).init_resource::<KeyHints>();
+				browser.open = true;
+				self.settings.clone()
+			}
+		};
 
-		let mut cpu = Chip8::new();
-		cpu.load_code(bytes);
-
-		app.insert_resource(Chip8CPU(cpu, Timer::from_seconds(1.0 / FPS, TimerMode::Repeating)))
-			.insert_resource(ClearColor(Color::srgb_u8(89, 0, 36)));
+		app.add_message::<LoadRom>()
+			.insert_resource(browser)
+			.insert_resource(self.rom_db.clone())
+			.insert_resource(BaseSettings(self.settings.clone()))
+			.insert_resource(self.keymap.clone())
+			.insert_resource(self.hotkeys.clone())
+			.init_resource::<EmulatorState>()
+			.init_resource::<MemoryViewer>()
+			.init_resource::<Inspector>()
+			.insert_resource(Recorder {
+				format: self.record_format,
+				recording: None,
+			})
+			.insert_resource(ScreenshotScale(self.screenshot_scale))
+			.insert_resource(match &self.movie
+			{
+				Some(movie) => MovieState::Playing {
+					movie: movie.clone(),
+					frame: 0,
+				},
+				None => MovieState::Idle,
+			})
+			.insert_resource(settings.palette.clear_color())
+			.insert_resource(settings)
+			.insert_resource(Time::<Fixed>::from_hz(FPS));
 		app.add_systems(Startup, setup);
-		app.add_systems(Update, (chip_input, chip_tick, chip_render).chain());
+		// FixedUpdate accumulates real time and runs once per elapsed 1/60s, catching up after hitches,
+		// so emulation speed no longer depends on how fast the app is updating
+		app.add_systems(FixedUpdate, (chip_input, chip_tick, capture_frame).chain());
+		app.add_systems(
+			Update,
+			(
+				(file_drop, browser_input, browser_click),
+				load_rom,
+				handle_hotkeys,
+				chip_render,
+				draw_browser,
+				update_title,
+			)
+				.chain(),
+		);
+		app.add_systems(
+			Update,
+			(memory_viewer_input, track_writes, draw_memory_viewer)
+				.chain()
+				.after(handle_hotkeys),
+		);
+		app.add_systems(Update, (inspector_input, draw_inspector).chain().after(handle_hotkeys));
+		app.add_systems(
+			Update,
+			(recorder_input, screenshot_input, movie_input).after(handle_hotkeys),
+		);
 
 		// app.add_plugins(FrameTimeDiagnosticsPlugin::default());
 	}
@@ -46,11 +180,12 @@ fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>, cpu: Res<Chi
 {
 	// commands.spawn(DiagnosticsOverlay::fps());
 	commands.spawn(Camera2d);
-	let img_data = render_image(cpu.0.display, cpu.0.high_res, LinearRgba::BLACK, LinearRgba::BLACK);
+	let img_data = render_image(&cpu.0.display, LinearRgba::BLACK, LinearRgba::BLACK);
+	// Kept in the main world too, so chip_render can rewrite the pixels in place
 	let handle = images.add(Image::from_dynamic(
 		img_data.into(),
 		true,
-		RenderAssetUsages::RENDER_WORLD,
+		RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
 	));
 	commands.insert_resource(DisplayImage(handle.clone()));
 	commands.queue_spawn_scene(bsn! {
@@ -59,95 +194,98 @@ fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>, cpu: Res<Chi
 	});
 }
 
-fn chip_render(mut cpu: ResMut<Chip8CPU>, mut images: ResMut<Assets<Image>>, img: Res<DisplayImage>, time: Res<Time>)
+fn chip_render(
+	mut cpu: ResMut<Chip8CPU>,
+	mut images: ResMut<Assets<Image>>,
+	img: Res<DisplayImage>,
+	settings: Res<Chip8Settings>,
+)
 {
-	if !cpu.1.tick(time.delta()).just_finished()
+	// Settings only change when a ROM is loaded or the palette is switched
+	if !cpu.0.need_draw && !settings.is_changed()
 	{
 		return;
 	}
-	let img_data = render_image(
-		cpu.0.display,
-		cpu.0.high_res,
-		LinearRgba::rgb(89. / 255., 0., 36. / 255.),
-		LinearRgba::rgb(1., 0., 100. / 255.),
+	cpu.0.need_draw = false;
+	let Some(data) = images
+		.get_mut(&img.0)
+		.and_then(|image| image.into_inner().data.as_mut())
+	else
+	{
+		return;
+	};
+	write_image(
+		&cpu.0.display,
+		settings.palette.background,
+		settings.palette.foreground,
+		data,
 	);
-	images
-		.insert(
-			img.0.id(),
-			Image::from_dynamic(img_data.into(), true, RenderAssetUsages::RENDER_WORLD),
-		)
-		.expect("Failed to insert image");
-	cpu.0.vblank();
 }
 
-fn chip_tick(mut cpu: ResMut<Chip8CPU>)
+fn chip_tick(
+	mut cpu: ResMut<Chip8CPU>,
+	mut state: ResMut<EmulatorState>,
+	settings: Res<Chip8Settings>,
+	browser: Res<RomBrowser>,
+	mut movie: ResMut<MovieState>,
+)
 {
-	if cpu.0.is_halted
+	if cpu.0.is_halted || browser.open || !state.should_run()
 	{
 		return;
 	}
-	cpu.0.run(60);
-}
-
-fn chip_input(mut cpu: ResMut<Chip8CPU>, key: Res<ButtonInput<KeyCode>>)
-{
-	cpu.0.set_key(0x1, key.pressed(KeyCode::Digit1));
-	cpu.0.set_key(0x2, key.pressed(KeyCode::Digit2));
-	cpu.0.set_key(0x3, key.pressed(KeyCode::Digit3));
-	cpu.0.set_key(0xC, key.pressed(KeyCode::Digit4));
-
-	cpu.0.set_key(0x4, key.pressed(KeyCode::KeyQ));
-	cpu.0.set_key(0x5, key.pressed(KeyCode::KeyW));
-	cpu.0.set_key(0x6, key.pressed(KeyCode::KeyE));
-	cpu.0.set_key(0xD, key.pressed(KeyCode::KeyR));
-
-	cpu.0.set_key(0x7, key.pressed(KeyCode::KeyA));
-	cpu.0.set_key(0x8, key.pressed(KeyCode::KeyS));
-	cpu.0.set_key(0x9, key.pressed(KeyCode::KeyD));
-	cpu.0.set_key(0xE, key.pressed(KeyCode::KeyF));
-
-	cpu.0.set_key(0xA, key.pressed(KeyCode::KeyZ));
-	cpu.0.set_key(0x0, key.pressed(KeyCode::KeyX));
-	cpu.0.set_key(0xB, key.pressed(KeyCode::KeyC));
-	cpu.0.set_key(0xF, key.pressed(KeyCode::KeyV));
+	movie.frame(&mut cpu.0);
+	cpu.0.run_frame(settings.ticks_per_frame);
 }
 
-pub fn render_image(
-	data: [u128; 64],
-	high_res: bool,
-	color1: LinearRgba,
-	color2: LinearRgba,
-) -> ImageBuffer<image::Rgba<u8>, Vec<u8>>
+fn load_rom(
+	mut commands: Commands,
+	mut requests: MessageReader<LoadRom>,
+	mut cpu: ResMut<Chip8CPU>,
+	mut state: ResMut<EmulatorState>,
+	mut browser: ResMut<RomBrowser>,
+	base: Res<BaseSettings>,
+	db: Res<RomDatabase>,
+)
 {
-	#[cfg(feature = "tracing")]
-	let _ = info_span!("Render Image").entered();
-	let mut image = ImageBuffer::new(DISPLAY_WIDTH_HIGHRES as u32, DISPLAY_HEIGHT_HIGHRES as u32);
-
-	image.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
-		if !high_res
-		{
-			let line = data[(y / 2) as usize] >> DISPLAY_WIDTH;
-			let mask = 1_u64 << ((DISPLAY_WIDTH as u32) - 1 - (x / 2));
-			let col = if line as u64 & mask == 0 { color1 } else { color2 };
-			*pixel = to_pixel(&col);
-		}
-		else
+	let Some(LoadRom(path)) = requests.read().last()
+	else
+	{
+		return;
+	};
+	let opened = fs::read(path)
+		.map_err(|err| format!("Failed to load {}: {}", path.display(), err))
+		.and_then(|bytes| Chip8Rom::open(path, bytes, &base.0, &db));
+	match opened
+	{
+		Ok((rom, settings)) =>
 		{
-			let line = data[y as usize];
-			let mask = 1_u128 << ((DISPLAY_WIDTH_HIGHRES as u32) - x);
-			let col = if line & mask == 0 { color1 } else { color2 };
-			*pixel = to_pixel(&col);
+			if let Some(info) = &rom.info
+			{
+				info!("Loaded {}", info.display_name());
+			}
+			cpu.0 = rom.machine.clone();
+			commands.insert_resource(settings.palette.clear_color());
+			commands.insert_resource(KeyHints::from_info(rom.info.as_ref()));
+			commands.insert_resource(settings);
+			commands.insert_resource(rom);
+			*state = EmulatorState::default();
+			browser.add_recent(path);
+			browser.open = false;
 		}
-	});
-	image
+		Err(err) => warn!("{}", err),
+	}
 }
 
-fn to_pixel(col: &LinearRgba) -> image::Rgba<u8>
+fn chip_input(mut cpu: ResMut<Chip8CPU>, key: Res<ButtonInput<KeyCode>>, keymap: Res<Keymap>, hints: Res<KeyHints>)
 {
-	image::Rgba([
-		(col.red * 255.0) as u8,
-		(col.green * 255.0) as u8,
-		(col.blue * 255.0) as u8,
-		255,
-	])
+	let mut pressed = keymap.0.map(|code| key.pressed(code));
+	for (code, k) in &hints.0
+	{
+		pressed[*k] |= key.pressed(*code);
+	}
+	for (k, state) in pressed.into_iter().enumerate()
+	{
+		cpu.0.set_key(k, state);
+	}
 }
@@ -0,0 +1,248 @@
+use std::{fs, ops::Range, path::PathBuf};
+
+use chip8_core::{
+	memory::{MAX_MEMORY, MemoryProfile},
+	quirks::{Platform, Quirks},
+};
+use clap::{CommandFactory, Parser, error::ErrorKind};
+
+use crate::{
+	chip8_display::Chip8Rom,
+	controls::Hotkeys,
+	movie::Movie,
+	recorder::RecordingFormat,
+	rom_db::RomDatabase,
+	settings::{Chip8Settings, Keymap, Overrides, Palette, load_keymap},
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "A CHIP-8 and SUPER-CHIP emulator")]
+pub struct Args
+{
+	/// ROM file to load
+	pub rom: Option<PathBuf>,
+
+	/// Platform whose quirks to emulate [chip8, schip], defaults to the ROM database entry or chip8
+	#[arg(short, long)]
+	pub platform: Option<Platform>,
+
+	/// Wrap sprites around the screen edges instead of clipping them, defaults to the ROM database entry or false
+	#[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+	pub wrap_sprites: Option<bool>,
+
+	/// Wait for the next frame after DXYN, defaults to the ROM database entry or the platform's behaviour
+	#[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+	pub display_wait: Option<bool>,
+
+	/// Instructions executed per 60Hz frame, defaults to the ROM database entry or 30
+	#[arg(short, long, value_parser = parse_speed)]
+	pub speed: Option<usize>,
+
+	/// Palette preset [default, mono, amber, green] or a BACKGROUND,FOREGROUND hex pair
+	#[arg(long)]
+	pub palette: Option<Palette>,
+
+	/// JSON file overriding keypad and hotkey bindings
+	#[arg(short, long)]
+	pub keymap: Option<PathBuf>,
+
+	/// RAM size and layout [standard, modern, vip, eti660, xochip]
+	#[arg(long, default_value = "standard")]
+	pub memory: MemoryProfile,
+
+	/// Address the ROM is loaded at and execution starts from, defaults to the ROM database entry or the memory
+	/// profile's
+	#[arg(long, value_parser = parse_address)]
+	pub start_address: Option<usize>,
+
+	/// Address of the hex digit font, usually 0x000 or 0x050 [default: 0x000]
+	#[arg(long, value_parser = parse_address)]
+	pub font_address: Option<usize>,
+
+	/// Window pixels per display pixel at low resolution
+	#[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..=100))]
+	pub scale: u32,
+
+	/// Start in borderless fullscreen
+	#[arg(short, long)]
+	pub fullscreen: bool,
+
+	/// Seed for the random number generator used by CXNN
+	#[arg(long)]
+	pub seed: Option<u64>,
+
+	/// Directory containing a copy of the community CHIP-8 database, replacing the bundled one
+	#[arg(long)]
+	pub rom_db: Option<PathBuf>,
+
+	/// Directory listed by the ROM browser
+	#[arg(long, default_value = ".")]
+	pub rom_dir: PathBuf,
+
+	/// Run without a window, printing the display when done
+	#[arg(long, requires = "rom")]
+	pub headless: bool,
+
+	/// Number of frames to run in headless mode, defaults to the length of the movie or 600
+	#[arg(long, requires = "headless")]
+	pub frames: Option<u64>,
+
+	/// Record the display to an animated .gif or .png (APNG) in headless mode
+	#[arg(long, requires = "headless")]
+	pub record: Option<PathBuf>,
+
+	/// Frames to record as START..END, counted from 0
+	#[arg(long, default_value = "0..", value_parser = parse_frame_range, requires = "record")]
+	pub record_frames: Range<u64>,
+
+	/// Replay keypad input and settings from a movie recorded with the movie hotkey
+	#[arg(long, requires = "rom")]
+	pub movie: Option<PathBuf>,
+
+	/// Format of clips recorded with the record hotkey [gif, apng]
+	#[arg(long, default_value = "gif")]
+	pub record_format: RecordingFormat,
+}
+
+/// Settings resolved from the command line, with every file already read and validated
+pub struct Config
+{
+	pub rom: Option<Chip8Rom>,
+	/// Settings before any ROM database hints are applied
+	pub settings: Chip8Settings,
+	pub rom_db: RomDatabase,
+	pub keymap: Keymap,
+	pub hotkeys: Hotkeys,
+	pub movie: Option<Movie>,
+}
+
+impl Args
+{
+	/// Reads the files referenced by the arguments, exiting with a usage error if anything is invalid
+	pub fn resolve(&self) -> Config
+	{
+		self.try_resolve()
+			.unwrap_or_else(|msg| Self::command().error(ErrorKind::InvalidValue, msg).exit())
+	}
+
+	fn try_resolve(&self) -> Result<Config, String>
+	{
+		let defaults = Chip8Settings::default();
+		let platform = self.platform.unwrap_or(defaults.platform);
+		let quirks = Quirks::for_platform(platform);
+		let mut settings = Chip8Settings {
+			platform,
+			quirks: Quirks {
+				wrap_sprites: self.wrap_sprites.unwrap_or(quirks.wrap_sprites),
+				display_wait: self.display_wait.unwrap_or(quirks.display_wait),
+				..quirks
+			},
+			ticks_per_frame: self.speed.unwrap_or(defaults.ticks_per_frame),
+			memory: MemoryProfile {
+				start_address: self.start_address.unwrap_or(self.memory.start_address),
+				font_address: self.font_address.unwrap_or(self.memory.font_address),
+				..self.memory
+			},
+			seed: self.seed,
+			palette: self.palette.unwrap_or(defaults.palette),
+			overrides: Overrides {
+				platform: self.platform.is_some(),
+				speed: self.speed.is_some(),
+				palette: self.palette.is_some(),
+				start_address: self.start_address.is_some(),
+				wrap_sprites: self.wrap_sprites.is_some(),
+				display_wait: self.display_wait.is_some(),
+			},
+		};
+		settings
+			.memory
+			.validate()
+			.map_err(|err| format!("invalid memory profile: {}", err))?;
+		let movie = self.movie.as_deref().map(Movie::load).transpose()?;
+		if let Some(movie) = &movie
+		{
+			movie.apply(&mut settings);
+		}
+		if let Some(path) = &self.record
+		{
+			RecordingFormat::from_path(path)?;
+		}
+		let rom_db = match &self.rom_db
+		{
+			Some(dir) => RomDatabase::load_dir(dir)?,
+			None => RomDatabase::bundled(),
+		};
+		let (keymap, hotkeys) = match &self.keymap
+		{
+			Some(path) => load_keymap(path)?,
+			None => (Keymap::default(), Hotkeys::default()),
+		};
+		let rom = match &self.rom
+		{
+			Some(path) =>
+			{
+				let bytes = fs::read(path).map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+				if let Some(movie) = &movie
+				{
+					movie.check_rom(&bytes)?;
+				}
+				Some(Chip8Rom::open(path, bytes, &settings, &rom_db)?.0)
+			}
+			None => None,
+		};
+		Ok(Config {
+			rom,
+			settings,
+			rom_db,
+			keymap,
+			hotkeys,
+			movie,
+		})
+	}
+}
+
+fn parse_speed(value: &str) -> Result<usize, String>
+{
+	match value.parse::<usize>()
+	{
+		Ok(speed) if (1..=100_000).contains(&speed) => Ok(speed),
+		_ => Err(format!("'{}' is not a number between 1 and 100000", value)),
+	}
+}
+
+/// Parses `START..END`, either end may be left out
+fn parse_frame_range(value: &str) -> Result<Range<u64>, String>
+{
+	let invalid = || format!("'{}' is not a frame range like 60..600", value);
+	let (start, end) = value.split_once("..").ok_or_else(invalid)?;
+	let start = match start
+	{
+		"" => 0,
+		start => start.parse().map_err(|_| invalid())?,
+	};
+	let end = match end
+	{
+		"" => u64::MAX,
+		end => end.parse().map_err(|_| invalid())?,
+	};
+	if start >= end
+	{
+		return Err(invalid());
+	}
+	Ok(start..end)
+}
+
+fn parse_address(value: &str) -> Result<usize, String>
+{
+	let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X"))
+	{
+		Some(hex) => usize::from_str_radix(hex, 16),
+		None => value.parse(),
+	};
+	match parsed
+	{
+		Ok(addr) if addr < MAX_MEMORY => Ok(addr),
+		Ok(_) => Err(format!("{} must be below {:#x}", value, MAX_MEMORY)),
+		Err(_) => Err(format!("'{}' is not a valid address", value)),
+	}
+}
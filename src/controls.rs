@@ -0,0 +1,139 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::Deserialize;
+
+use crate::chip8_display::{Chip8CPU, Chip8Rom, LoadRom};
+
+/// Keys used to control the emulator itself, kept away from the default keypad layout
+#[derive(Resource, Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hotkeys
+{
+	pub pause: KeyCode,
+	pub frame_advance: KeyCode,
+	pub soft_reset: KeyCode,
+	pub hard_reset: KeyCode,
+	pub rom_browser: KeyCode,
+	pub memory_viewer: KeyCode,
+	pub inspector: KeyCode,
+	pub record: KeyCode,
+	pub screenshot: KeyCode,
+	pub record_movie: KeyCode,
+}
+
+impl Default for Hotkeys
+{
+	fn default() -> Self
+	{
+		Self {
+			pause: KeyCode::KeyP,
+			frame_advance: KeyCode::KeyN,
+			soft_reset: KeyCode::F5,
+			hard_reset: KeyCode::F6,
+			rom_browser: KeyCode::F1,
+			memory_viewer: KeyCode::F2,
+			inspector: KeyCode::F3,
+			record: KeyCode::F9,
+			screenshot: KeyCode::F12,
+			record_movie: KeyCode::F7,
+		}
+	}
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct EmulatorState
+{
+	pub paused: bool,
+	/// Set while paused to run exactly one more frame on the next fixed tick
+	pub advance_frame: bool,
+}
+
+impl EmulatorState
+{
+	/// Whether the next fixed tick should run a frame, consuming a pending frame advance
+	pub fn should_run(&mut self) -> bool
+	{
+		if !self.paused
+		{
+			return true;
+		}
+		std::mem::take(&mut self.advance_frame)
+	}
+}
+
+pub fn handle_hotkeys(
+	key: Res<ButtonInput<KeyCode>>,
+	hotkeys: Res<Hotkeys>,
+	mut state: ResMut<EmulatorState>,
+	mut cpu: ResMut<Chip8CPU>,
+	mut load: MessageWriter<LoadRom>,
+	rom: Option<Res<Chip8Rom>>,
+)
+{
+	let Some(rom) = rom
+	else
+	{
+		return;
+	};
+	if key.just_pressed(hotkeys.pause)
+	{
+		state.paused = !state.paused;
+	}
+	if key.just_pressed(hotkeys.frame_advance) && state.paused
+	{
+		state.advance_frame = true;
+	}
+	if key.just_pressed(hotkeys.soft_reset)
+	{
+		cpu.0 = rom.machine.clone();
+	}
+	if key.just_pressed(hotkeys.hard_reset)
+	{
+		// A hard reset reloads the ROM from disk, handy when iterating on a ROM
+		load.write(LoadRom(rom.path.clone().into()));
+	}
+}
+
+pub fn update_title(
+	state: Res<EmulatorState>,
+	cpu: Res<Chip8CPU>,
+	rom: Option<Res<Chip8Rom>>,
+	mut windows: Query<&mut Window, With<PrimaryWindow>>,
+)
+{
+	if !state.is_changed() && !cpu.is_changed() && !rom.as_ref().is_some_and(|r| r.is_changed())
+	{
+		return;
+	}
+	let Some(rom) = rom
+	else
+	{
+		set_title(&mut windows, "Chip 8 - No ROM");
+		return;
+	};
+	let name = match &rom.info
+	{
+		Some(info) => info.display_name(),
+		None => rom.path.rsplit(['/', '\\']).next().unwrap_or_default().to_string(),
+	};
+	let mut title = format!("Chip 8 - {}", name);
+	if cpu.0.is_halted
+	{
+		title.push_str(" [Halted]");
+	}
+	else if state.paused
+	{
+		title.push_str(" [Paused]");
+	}
+	set_title(&mut windows, &title);
+}
+
+fn set_title(windows: &mut Query<&mut Window, With<PrimaryWindow>>, title: &str)
+{
+	for mut window in windows
+	{
+		if window.title != title
+		{
+			window.title = title.to_string();
+		}
+	}
+}
@@ -0,0 +1,199 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+use chip8_core::{chip8::Chip8, disassembler::disassemble};
+
+use crate::{
+	chip8_display::Chip8CPU,
+	controls::Hotkeys,
+	overlay::{self, DIM_TEXT_COLOR, HEADER_COLOR, TEXT_COLOR},
+};
+
+/// Instructions shown from the program counter onwards
+const DISASSEMBLY_LINES: usize = 8;
+/// Keypad in its physical layout
+const KEYPAD: [[usize; 4]; 4] = [
+	[0x1, 0x2, 0x3, 0xC],
+	[0x4, 0x5, 0x6, 0xD],
+	[0x7, 0x8, 0x9, 0xE],
+	[0xA, 0x0, 0xB, 0xF],
+];
+
+#[derive(Resource, Default)]
+pub struct Inspector
+{
+	pub open: bool,
+}
+
+#[derive(Component)]
+pub struct InspectorPanel;
+
+#[derive(Component)]
+pub enum InspectorSection
+{
+	Registers,
+	Stack,
+	Keys,
+	Disassembly,
+}
+
+pub fn inspector_input(key: Res<ButtonInput<KeyCode>>, hotkeys: Res<Hotkeys>, mut inspector: ResMut<Inspector>)
+{
+	if key.just_pressed(hotkeys.inspector)
+	{
+		inspector.open = !inspector.open;
+	}
+}
+
+pub fn draw_inspector(
+	mut commands: Commands,
+	inspector: Res<Inspector>,
+	cpu: Res<Chip8CPU>,
+	panels: Query<Entity, With<InspectorPanel>>,
+	mut sections: Query<(&InspectorSection, &mut TextSpan)>,
+)
+{
+	if inspector.open == panels.is_empty()
+	{
+		if inspector.open
+		{
+			spawn_panel(&mut commands);
+		}
+		else
+		{
+			for panel in &panels
+			{
+				commands.entity(panel).despawn();
+			}
+		}
+		return;
+	}
+	if !inspector.open
+	{
+		return;
+	}
+
+	let cpu = &cpu.0;
+	for (section, mut text) in &mut sections
+	{
+		let content = match section
+		{
+			InspectorSection::Registers => registers(cpu),
+			InspectorSection::Stack => stack(cpu),
+			InspectorSection::Keys => keys(cpu),
+			InspectorSection::Disassembly => disassembly(cpu),
+		};
+		overlay::set_text(&mut text, content);
+	}
+}
+
+fn registers(cpu: &Chip8) -> String
+{
+	let mut text = String::new();
+	for (i, chunk) in cpu.registers.chunks(4).enumerate()
+	{
+		for (j, value) in chunk.iter().enumerate()
+		{
+			let _ = write!(text, "V{:X} {:02X}  ", i * 4 + j, value);
+		}
+		text.push('\n');
+	}
+	let width = cpu.memory().address_digits();
+	let _ = writeln!(
+		text,
+		"I  {:0width$X}  PC {:0width$X}  SP {:X}\nDT {:02X}   ST {:02X}   {}{}",
+		cpu.reg_i,
+		cpu.program_counter,
+		cpu.stack_pointer,
+		cpu.reg_dt,
+		cpu.reg_st,
+		if cpu.high_res() { "HIRES" } else { "LORES" },
+		if cpu.is_halted { "  HALTED" } else { "" },
+	);
+	text
+}
+
+fn stack(cpu: &Chip8) -> String
+{
+	if cpu.stack_pointer == 0
+	{
+		return "(empty)\n".into();
+	}
+	// CALL pushes its own address, execution resumes at the instruction after it
+	let width = cpu.memory().address_digits();
+	let mut text = String::new();
+	for (depth, entry) in cpu.stack.iter().enumerate().take(cpu.stack_pointer + 1).skip(1).rev()
+	{
+		let _ = writeln!(
+			text,
+			"{:X}: {:0width$X} -> {:0width$X}",
+			depth,
+			entry,
+			entry.wrapping_add(2)
+		);
+	}
+	text
+}
+
+fn keys(cpu: &Chip8) -> String
+{
+	let mut text = String::new();
+	for row in KEYPAD
+	{
+		for key in row
+		{
+			let _ = match cpu.keys[key]
+			{
+				true => write!(text, "{:X} ", key),
+				false => write!(text, ". "),
+			};
+		}
+		text.push('\n');
+	}
+	text
+}
+
+fn disassembly(cpu: &Chip8) -> String
+{
+	let width = cpu.memory().address_digits();
+	let mut text = String::new();
+	for line in 0..DISASSEMBLY_LINES
+	{
+		let addr = cpu.program_counter + line * 2;
+		let Some(instruction) = cpu.instruction_at(addr)
+		else
+		{
+			break;
+		};
+		let marker = if line == 0 { '>' } else { ' ' };
+		let _ = writeln!(
+			text,
+			"{}{:0width$X} {:04X} {}",
+			marker,
+			addr,
+			instruction,
+			disassemble(instruction, cpu.quirks)
+		);
+	}
+	text
+}
+
+fn spawn_panel(commands: &mut Commands)
+{
+	commands
+		.spawn((InspectorPanel, overlay::panel(true)))
+		.with_children(|panel| {
+			for (title, section) in [
+				("Registers", InspectorSection::Registers),
+				("Stack", InspectorSection::Stack),
+				("Keys", InspectorSection::Keys),
+				("Next instructions", InspectorSection::Disassembly),
+			]
+			{
+				panel.spawn((Text::new(title), overlay::font(), TextColor(HEADER_COLOR)));
+				panel
+					.spawn((Text::default(), overlay::font(), TextColor(DIM_TEXT_COLOR)))
+					.with_child((section, overlay::span("", TEXT_COLOR)));
+			}
+		});
+}
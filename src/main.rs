@@ -1,20 +1,57 @@
-pub mod chip8;
 pub mod chip8_display;
-pub mod tests;
+pub mod cli;
+pub mod controls;
+pub mod inspector;
+pub mod memory_viewer;
+pub mod movie;
+pub mod overlay;
+pub mod recorder;
+pub mod render;
+pub mod rom_browser;
+pub mod rom_db;
+pub mod screenshot;
+pub mod settings;
+#[cfg(test)]
+mod tests;
 
-use crate::chip8::DISPLAY_HEIGHT;
-use bevy::window::WindowResolution;
+use bevy::window::{MonitorSelection, WindowMode, WindowResolution};
 use bevy::{
 	image::{ImageAddressMode, ImageFilterMode, ImageSamplerDescriptor},
 	prelude::*,
 	window::PresentMode,
 };
+use chip8_core::chip8::DISPLAY_HEIGHT;
 use chip8_display::Chip8Plugin;
-const WINDOW_SIZE: u32 = 20 * DISPLAY_HEIGHT as u32;
+use clap::Parser;
+use cli::{Args, Config};
+use recorder::{Recording, RecordingFormat};
 
 fn main()
 {
-	// let mut emu = Chip8Display::new(bytes);
+	let args = Args::parse();
+	let config = args.resolve();
+	if config.rom_db.is_empty()
+	{
+		eprintln!(
+			"No ROM database: copy the community CHIP-8 database's `database` directory to assets/chip8-database or \
+			 pass it with --rom-db to pick settings for known ROMs"
+		);
+	}
+	if args.headless
+	{
+		run_headless(config, &args);
+		return;
+	}
+
+	let window_size = args.scale * DISPLAY_HEIGHT as u32;
+	let mode = if args.fullscreen
+	{
+		WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+	}
+	else
+	{
+		WindowMode::Windowed
+	};
 	App::new()
 		.add_plugins((
 			DefaultPlugins
@@ -22,9 +59,10 @@ fn main()
 					primary_window: Some(Window {
 						title: "Chip 8".into(),
 						name: Some("Chip8".into()),
-						resolution: WindowResolution::new(WINDOW_SIZE * 2, WINDOW_SIZE),
-						present_mode: PresentMode::AutoNoVsync,
+						resolution: WindowResolution::new(window_size * 2, window_size),
+						present_mode: PresentMode::AutoVsync,
 						resizable: true,
+						mode,
 						..default()
 					}),
 					..default()
@@ -37,7 +75,76 @@ fn main()
 						..default()
 					},
 				}),
-			Chip8Plugin,
+			Chip8Plugin {
+				rom: config.rom,
+				settings: config.settings,
+				rom_db: config.rom_db,
+				keymap: config.keymap,
+				hotkeys: config.hotkeys,
+				rom_dir: args.rom_dir,
+				record_format: args.record_format,
+				screenshot_scale: args.scale,
+				movie: config.movie,
+			},
 		))
 		.run();
 }
+
+/// Runs the emulator as fast as possible without a window, then prints the final display
+fn run_headless(config: Config, args: &Args)
+{
+	let rom = config.rom.expect("clap requires a ROM in headless mode");
+	let settings = config.settings.for_rom(rom.info.as_ref());
+	if let Some(info) = &rom.info
+	{
+		println!(
+			"{} ({}, {} ticks per frame)",
+			info.display_name(),
+			settings.platform,
+			settings.ticks_per_frame
+		);
+	}
+
+	let mut recording = Recording::default();
+	let frames = args
+		.frames
+		.or(config.movie.as_ref().map(|movie| movie.frames.len() as u64))
+		.unwrap_or(600);
+	let mut cpu = rom.machine;
+	for frame in 0..frames
+	{
+		if cpu.is_halted
+		{
+			break;
+		}
+		if let Some(movie) = &config.movie
+		{
+			cpu.keys = movie.keys(frame as usize);
+		}
+		cpu.run_frame(settings.ticks_per_frame);
+		if args.record.is_some() && args.record_frames.contains(&frame)
+		{
+			recording.capture(&cpu);
+		}
+	}
+	cpu.print_display();
+
+	if let Some(path) = &args.record
+	{
+		let format = RecordingFormat::from_path(path).expect("validated when resolving arguments");
+		match recording.save(path, format, settings.palette)
+		{
+			Ok(()) => println!(
+				"Saved {} frames ({} unique) to {}",
+				recording.len(),
+				recording.unique_frames(),
+				path.display()
+			),
+			Err(err) =>
+			{
+				eprintln!("Recording not saved: {}", err);
+				std::process::exit(1);
+			}
+		}
+	}
+}
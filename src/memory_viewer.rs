@@ -0,0 +1,328 @@
+use bevy::prelude::*;
+use chip8_core::memory::MAX_MEMORY;
+
+use crate::{
+	chip8_display::Chip8CPU,
+	controls::{EmulatorState, Hotkeys},
+	overlay::{self, DIM_TEXT_COLOR, HEADER_COLOR, I_COLOR, PC_COLOR, TEXT_COLOR},
+};
+
+const BYTES_PER_ROW: usize = 8;
+const VISIBLE_ROWS: usize = 32;
+/// Frames a written byte stays highlighted for
+const FLASH_FRAMES: u8 = 30;
+
+const CURSOR_COLOR: Color = Color::srgb(0.85, 0.85, 0.85);
+const WRITE_COLOR: Color = Color::srgb(1., 0.85, 0.);
+
+const HEX_KEYS: [KeyCode; 16] = [
+	KeyCode::Digit0,
+	KeyCode::Digit1,
+	KeyCode::Digit2,
+	KeyCode::Digit3,
+	KeyCode::Digit4,
+	KeyCode::Digit5,
+	KeyCode::Digit6,
+	KeyCode::Digit7,
+	KeyCode::Digit8,
+	KeyCode::Digit9,
+	KeyCode::KeyA,
+	KeyCode::KeyB,
+	KeyCode::KeyC,
+	KeyCode::KeyD,
+	KeyCode::KeyE,
+	KeyCode::KeyF,
+];
+
+/// Hex dump of RAM which follows the program counter while running, and can be edited while paused
+#[derive(Resource)]
+pub struct MemoryViewer
+{
+	pub open: bool,
+	/// Address being edited while paused
+	pub cursor: usize,
+	/// High nibble typed at the cursor, waiting for the low one
+	pending: Option<u8>,
+	first_row: usize,
+	snapshot: Box<[u8; MAX_MEMORY]>,
+	/// Frames left to highlight each byte since it was last written
+	write_age: Box<[u8; MAX_MEMORY]>,
+}
+
+impl Default for MemoryViewer
+{
+	fn default() -> Self
+	{
+		Self {
+			open: false,
+			cursor: 0x200,
+			pending: None,
+			first_row: 0x200 / BYTES_PER_ROW,
+			snapshot: Box::new([0; MAX_MEMORY]),
+			write_age: Box::new([0; MAX_MEMORY]),
+		}
+	}
+}
+
+impl MemoryViewer
+{
+	/// Scrolls just enough to keep `addr` on screen
+	fn scroll_to(&mut self, addr: usize)
+	{
+		let row = addr / BYTES_PER_ROW;
+		if row < self.first_row
+		{
+			self.first_row = row;
+		}
+		else if row >= self.first_row + VISIBLE_ROWS
+		{
+			self.first_row = row + 1 - VISIBLE_ROWS;
+		}
+	}
+
+	fn move_cursor(&mut self, offset: isize, ram_size: usize)
+	{
+		self.cursor = self.cursor.saturating_add_signed(offset).min(ram_size - 1);
+		self.pending = None;
+		self.scroll_to(self.cursor);
+	}
+}
+
+#[derive(Component)]
+pub struct MemoryViewerPanel;
+
+/// Which part of the dump a span shows, rows are relative to the first visible one
+#[derive(Component)]
+pub enum MemorySpan
+{
+	Address(usize),
+	Byte
+	{
+		row: usize,
+		col: usize,
+	},
+	Ascii(usize),
+	Sprite(usize),
+}
+
+pub fn memory_viewer_input(
+	key: Res<ButtonInput<KeyCode>>,
+	hotkeys: Res<Hotkeys>,
+	state: Res<EmulatorState>,
+	mut viewer: ResMut<MemoryViewer>,
+	mut cpu: ResMut<Chip8CPU>,
+)
+{
+	if key.just_pressed(hotkeys.memory_viewer)
+	{
+		viewer.open = !viewer.open;
+	}
+	if !viewer.open || !state.paused
+	{
+		return;
+	}
+
+	let ram_size = cpu.0.memory().ram_size;
+	let row = BYTES_PER_ROW as isize;
+	let page = row * VISIBLE_ROWS as isize;
+	for (code, offset) in [
+		(KeyCode::ArrowLeft, -1),
+		(KeyCode::ArrowRight, 1),
+		(KeyCode::ArrowUp, -row),
+		(KeyCode::ArrowDown, row),
+		(KeyCode::PageUp, -page),
+		(KeyCode::PageDown, page),
+	]
+	{
+		if key.just_pressed(code)
+		{
+			viewer.move_cursor(offset, ram_size);
+		}
+	}
+
+	if let Some(nibble) = HEX_KEYS.iter().position(|code| key.just_pressed(*code))
+	{
+		let nibble = nibble as u8;
+		match viewer.pending.take()
+		{
+			None => viewer.pending = Some(nibble),
+			Some(high) =>
+			{
+				let cursor = viewer.cursor;
+				cpu.0.ram[cursor] = (high << 4) | nibble;
+				viewer.move_cursor(1, ram_size);
+			}
+		}
+	}
+}
+
+/// Diffs RAM against the last frame so self-modifying code stands out
+pub fn track_writes(mut viewer: ResMut<MemoryViewer>, cpu: Res<Chip8CPU>)
+{
+	let viewer = &mut *viewer;
+	for ((old, new), age) in viewer
+		.snapshot
+		.iter_mut()
+		.zip(cpu.0.ram[..cpu.0.memory().ram_size].iter())
+		.zip(viewer.write_age.iter_mut())
+	{
+		if old != new
+		{
+			*old = *new;
+			*age = FLASH_FRAMES;
+		}
+		else
+		{
+			*age = age.saturating_sub(1);
+		}
+	}
+}
+
+pub fn draw_memory_viewer(
+	mut commands: Commands,
+	mut viewer: ResMut<MemoryViewer>,
+	state: Res<EmulatorState>,
+	cpu: Res<Chip8CPU>,
+	panels: Query<Entity, With<MemoryViewerPanel>>,
+	mut spans: Query<(&MemorySpan, &mut TextSpan, &mut TextColor, &mut TextBackgroundColor)>,
+)
+{
+	if viewer.open == panels.is_empty()
+	{
+		if viewer.open
+		{
+			spawn_panel(&mut commands);
+		}
+		else
+		{
+			for panel in &panels
+			{
+				commands.entity(panel).despawn();
+			}
+		}
+		return;
+	}
+	if !viewer.open
+	{
+		return;
+	}
+
+	let cpu = &cpu.0;
+	let ram = &cpu.ram[..cpu.memory().ram_size];
+	let editing = state.paused;
+	if !editing
+	{
+		viewer.scroll_to(cpu.program_counter.min(ram.len() - 1));
+	}
+	let first = viewer.first_row * BYTES_PER_ROW;
+	let reg_i = cpu.reg_i as usize;
+	let width = cpu.memory().address_digits();
+
+	for (span, mut text, mut color, mut background) in &mut spans
+	{
+		match *span
+		{
+			MemorySpan::Address(row) =>
+			{
+				overlay::set_text(&mut text, format!("{:0width$X} ", first + row * BYTES_PER_ROW));
+			}
+			MemorySpan::Byte { row, col } =>
+			{
+				let addr = first + row * BYTES_PER_ROW + col;
+				let Some(value) = ram.get(addr)
+				else
+				{
+					overlay::set_text(&mut text, "   ".into());
+					continue;
+				};
+				let is_cursor = editing && addr == viewer.cursor;
+				let label = match viewer.pending
+				{
+					Some(high) if is_cursor => format!("{:X}_ ", high),
+					_ => format!("{:02X} ", value),
+				};
+				overlay::set_text(&mut text, label);
+
+				let age = viewer.write_age[addr];
+				let fg = if is_cursor
+				{
+					Color::BLACK
+				}
+				else if age > 0
+				{
+					TEXT_COLOR.mix(&WRITE_COLOR, age as f32 / FLASH_FRAMES as f32)
+				}
+				else
+				{
+					TEXT_COLOR
+				};
+				let bg = if is_cursor
+				{
+					CURSOR_COLOR
+				}
+				else if addr == cpu.program_counter || addr == cpu.program_counter + 1
+				{
+					PC_COLOR
+				}
+				else if addr == reg_i
+				{
+					I_COLOR
+				}
+				else
+				{
+					Color::NONE
+				};
+				color.set_if_neq(TextColor(fg));
+				background.set_if_neq(TextBackgroundColor(bg));
+			}
+			MemorySpan::Ascii(row) =>
+			{
+				let start = (first + row * BYTES_PER_ROW).min(ram.len());
+				let end = (start + BYTES_PER_ROW).min(ram.len());
+				let line: String = ram[start..end]
+					.iter()
+					.map(|b| if b.is_ascii_graphic() { *b as char } else { '.' })
+					.collect();
+				overlay::set_text(&mut text, format!("{:<8}  ", line));
+			}
+			MemorySpan::Sprite(row) =>
+			{
+				// One byte per row starting at I, so sprites read top to bottom like on screen
+				let line = match ram.get(reg_i + row)
+				{
+					Some(b) => (0..8)
+						.rev()
+						.map(|bit| if b >> bit & 1 == 1 { '#' } else { '.' })
+						.collect(),
+					None => String::new(),
+				};
+				overlay::set_text(&mut text, line);
+			}
+		}
+	}
+}
+
+fn spawn_panel(commands: &mut Commands)
+{
+	commands
+		.spawn((MemoryViewerPanel, overlay::panel(false)))
+		.with_children(|panel| {
+			panel.spawn((
+				Text::new("ADR 00 01 02 03 04 05 06 07 ASCII     SPRITE@I"),
+				overlay::font(),
+				TextColor(HEADER_COLOR),
+			));
+			for row in 0..VISIBLE_ROWS
+			{
+				panel.spawn((Text::default(), overlay::font())).with_children(|line| {
+					line.spawn((MemorySpan::Address(row), overlay::span("", DIM_TEXT_COLOR)));
+					for col in 0..BYTES_PER_ROW
+					{
+						line.spawn((MemorySpan::Byte { row, col }, overlay::span("", TEXT_COLOR)));
+					}
+					line.spawn((MemorySpan::Ascii(row), overlay::span("", DIM_TEXT_COLOR)));
+					line.spawn((MemorySpan::Sprite(row), overlay::span("", TEXT_COLOR)));
+				});
+			}
+		});
+}
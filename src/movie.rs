@@ -0,0 +1,196 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use chip8_core::{
+	chip8::Chip8,
+	memory::MemoryProfile,
+	quirks::{Platform, Quirks},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	chip8_display::{Chip8CPU, Chip8Rom, LoadRom},
+	controls::Hotkeys,
+	rom_db::sha1_hex,
+	screenshot::capture_stem,
+	settings::Chip8Settings,
+};
+
+/// Keypad input for every emulated frame along with everything else that affects emulation, so a session can be
+/// replayed exactly
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Movie
+{
+	pub rom_sha1: String,
+	pub platform: Platform,
+	pub quirks: Quirks,
+	pub ticks_per_frame: usize,
+	pub memory: MemoryProfile,
+	pub seed: Option<u64>,
+	/// Keypad state per frame, bit N is set while key N is held
+	pub frames: Vec<u16>,
+}
+
+impl Movie
+{
+	pub fn new(rom: &[u8], settings: &Chip8Settings) -> Self
+	{
+		Self {
+			rom_sha1: sha1_hex(rom),
+			platform: settings.platform,
+			quirks: settings.quirks,
+			ticks_per_frame: settings.ticks_per_frame,
+			memory: settings.memory,
+			seed: settings.seed,
+			frames: Vec::new(),
+		}
+	}
+
+	pub fn load(path: &Path) -> Result<Self, String>
+	{
+		let json = fs::read_to_string(path).map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+		serde_json::from_str(&json).map_err(|err| format!("invalid movie {}: {}", path.display(), err))
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), String>
+	{
+		let json = serde_json::to_string(self).map_err(|err| err.to_string())?;
+		fs::write(path, json).map_err(|err| format!("failed to write {}: {}", path.display(), err))
+	}
+
+	pub fn check_rom(&self, rom: &[u8]) -> Result<(), String>
+	{
+		let hash = sha1_hex(rom);
+		if hash == self.rom_sha1
+		{
+			Ok(())
+		}
+		else
+		{
+			Err(format!(
+				"the movie was recorded with a ROM with SHA-1 {}, this one is {}",
+				self.rom_sha1, hash
+			))
+		}
+	}
+
+	/// Uses the recorded settings, taking precedence over both the command line and the ROM database
+	pub fn apply(&self, settings: &mut Chip8Settings)
+	{
+		settings.platform = self.platform;
+		settings.quirks = self.quirks;
+		settings.ticks_per_frame = self.ticks_per_frame;
+		settings.memory = self.memory;
+		settings.seed = self.seed;
+		settings.overrides.platform = true;
+		settings.overrides.speed = true;
+		settings.overrides.start_address = true;
+		settings.overrides.wrap_sprites = true;
+		settings.overrides.display_wait = true;
+	}
+
+	pub fn record(&mut self, keys: &[bool; 16])
+	{
+		let mask = keys
+			.iter()
+			.enumerate()
+			.fold(0, |mask, (key, pressed)| mask | (*pressed as u16) << key);
+		self.frames.push(mask);
+	}
+
+	/// Keypad state for a frame, with every key released once the movie has ended
+	pub fn keys(&self, frame: usize) -> [bool; 16]
+	{
+		let mask = self.frames.get(frame).copied().unwrap_or_default();
+		std::array::from_fn(|key| mask >> key & 1 == 1)
+	}
+}
+
+#[derive(Resource, Default)]
+pub enum MovieState
+{
+	#[default]
+	Idle,
+	Recording(Movie),
+	Playing
+	{
+		movie: Movie,
+		frame: usize,
+	},
+}
+
+impl MovieState
+{
+	/// Records or overrides the keypad for the frame about to run
+	pub fn frame(&mut self, cpu: &mut Chip8)
+	{
+		match self
+		{
+			MovieState::Idle => (),
+			MovieState::Recording(movie) => movie.record(&cpu.keys),
+			MovieState::Playing { movie, frame } if *frame < movie.frames.len() =>
+			{
+				cpu.keys = movie.keys(*frame);
+				*frame += 1;
+			}
+			MovieState::Playing { .. } =>
+			{
+				info!("Movie finished");
+				*self = MovieState::Idle;
+			}
+		}
+	}
+
+	/// Ends playback or recording, saving the movie if one was being recorded
+	fn stop(&mut self, rom: Option<&Chip8Rom>)
+	{
+		match std::mem::take(self)
+		{
+			MovieState::Recording(movie) =>
+			{
+				let path = format!("{}.movie.json", capture_stem(rom));
+				match movie.save(Path::new(&path))
+				{
+					Ok(()) => info!("Saved {} frame movie to {}", movie.frames.len(), path),
+					Err(err) => error!("Movie not saved: {}", err),
+				}
+			}
+			MovieState::Playing { .. } => info!("Movie stopped"),
+			MovieState::Idle => (),
+		}
+	}
+}
+
+/// Starts a recording from a fresh machine, and stops recording or playback when the machine is reset some other way
+pub fn movie_input(
+	key: Res<ButtonInput<KeyCode>>,
+	hotkeys: Res<Hotkeys>,
+	mut loads: MessageReader<LoadRom>,
+	mut movie: ResMut<MovieState>,
+	mut cpu: ResMut<Chip8CPU>,
+	settings: Res<Chip8Settings>,
+	rom: Option<Res<Chip8Rom>>,
+)
+{
+	let reset = key.just_pressed(hotkeys.soft_reset) || key.just_pressed(hotkeys.hard_reset);
+	if loads.read().count() > 0 || reset
+	{
+		movie.stop(rom.as_deref());
+		return;
+	}
+	if !key.just_pressed(hotkeys.record_movie)
+	{
+		return;
+	}
+	match (&*movie, rom)
+	{
+		(MovieState::Recording(_), rom) => movie.stop(rom.as_deref()),
+		(_, Some(rom)) =>
+		{
+			info!("Recording movie");
+			cpu.0 = rom.machine.clone();
+			*movie = MovieState::Recording(Movie::new(&rom.bytes, &settings));
+		}
+		(_, None) => warn!("Load a ROM before recording a movie"),
+	}
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+
+pub const OVERLAY_BACKGROUND: Color = Color::srgba(0.05, 0.0, 0.03, 0.85);
+pub const TEXT_COLOR: Color = Color::srgb(0.85, 0.85, 0.85);
+pub const DIM_TEXT_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+pub const HEADER_COLOR: Color = Color::srgb(1., 0., 0.4);
+pub const PC_COLOR: Color = Color::srgb(0.1, 0.45, 0.1);
+pub const I_COLOR: Color = Color::srgb(0.1, 0.2, 0.55);
+pub const FONT_SIZE: f32 = 14.;
+
+/// An absolutely positioned column for debug panels drawn over the display
+pub fn panel(left: bool) -> impl Bundle
+{
+	let mut node = Node {
+		position_type: PositionType::Absolute,
+		top: px(8),
+		flex_direction: FlexDirection::Column,
+		padding: UiRect::all(px(8)),
+		..default()
+	};
+	if left
+	{
+		node.left = px(8);
+	}
+	else
+	{
+		node.right = px(8);
+	}
+	(node, BackgroundColor(OVERLAY_BACKGROUND), GlobalZIndex(5))
+}
+
+pub fn font() -> TextFont
+{
+	TextFont {
+		font_size: FontSize::Px(FONT_SIZE),
+		..default()
+	}
+}
+
+/// A monospaced span, the default font is Fira Mono so columns line up
+pub fn span(text: impl Into<String>, color: Color) -> impl Bundle
+{
+	(
+		TextSpan::new(text),
+		font(),
+		TextColor(color),
+		TextBackgroundColor(Color::NONE),
+	)
+}
+
+/// Updates a span only when its text differs, so unchanged text isn't laid out again every frame
+pub fn set_text(span: &mut Mut<TextSpan>, text: String)
+{
+	if span.0 != text
+	{
+		span.0 = text;
+	}
+}
@@ -0,0 +1,238 @@
+use std::{
+	fs::File,
+	io::BufWriter,
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use bevy::prelude::*;
+use chip8_core::{Framebuffer, chip8::Chip8};
+use image::{
+	Delay, Frame, RgbaImage,
+	codecs::gif::{GifEncoder, Repeat},
+	imageops::{self, FilterType},
+};
+
+use crate::{
+	chip8_display::{Chip8CPU, Chip8Rom},
+	controls::Hotkeys,
+	render::render_image,
+	screenshot::capture_stem,
+	settings::{Chip8Settings, Palette},
+};
+
+/// Output pixels per display pixel at high resolution, so clips are readable in bug reports
+const RECORDING_SCALE: u32 = 4;
+const FRAME_RATE: u32 = 60;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordingFormat
+{
+	#[default]
+	Gif,
+	Apng,
+}
+
+impl RecordingFormat
+{
+	pub fn extension(&self) -> &'static str
+	{
+		match self
+		{
+			RecordingFormat::Gif => "gif",
+			RecordingFormat::Apng => "png",
+		}
+	}
+
+	/// Picks the format from a file extension, `.png` and `.apng` both write APNG
+	pub fn from_path(path: &Path) -> Result<Self, String>
+	{
+		let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+		extension
+			.parse()
+			.map_err(|_| format!("{} should end in .gif, .png or .apng", path.display()))
+	}
+}
+
+impl FromStr for RecordingFormat
+{
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		match s.to_ascii_lowercase().as_str()
+		{
+			"gif" => Ok(RecordingFormat::Gif),
+			"apng" | "png" => Ok(RecordingFormat::Apng),
+			_ => Err(format!("unknown recording format '{}', expected gif or apng", s)),
+		}
+	}
+}
+
+/// Displays captured once per 60Hz frame, with consecutive identical frames merged
+#[derive(Default)]
+pub struct Recording
+{
+	/// Display contents and how many frames they were shown for
+	frames: Vec<(Framebuffer, u32)>,
+}
+
+impl Recording
+{
+	pub fn capture(&mut self, cpu: &Chip8)
+	{
+		match self.frames.last_mut()
+		{
+			Some((display, duration)) if *display == cpu.display =>
+			{
+				*duration += 1;
+			}
+			_ => self.frames.push((cpu.display, 1)),
+		}
+	}
+
+	/// Number of 60Hz frames recorded, including merged ones
+	pub fn len(&self) -> u32
+	{
+		self.frames.iter().map(|(_, duration)| duration).sum()
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+		self.frames.is_empty()
+	}
+
+	/// Number of distinct images that will be written
+	pub fn unique_frames(&self) -> usize
+	{
+		self.frames.len()
+	}
+
+	pub fn save(&self, path: &Path, format: RecordingFormat, palette: Palette) -> Result<(), String>
+	{
+		if self.is_empty()
+		{
+			return Err("nothing was recorded".into());
+		}
+		let file = File::create(path).map_err(|err| format!("failed to create {}: {}", path.display(), err))?;
+		let writer = BufWriter::new(file);
+		let images = self.frames.iter().map(|(display, duration)| {
+			let image = render_image(display, palette.background, palette.foreground);
+			let (width, height) = image.dimensions();
+			let image = imageops::resize(
+				&image,
+				width * RECORDING_SCALE,
+				height * RECORDING_SCALE,
+				FilterType::Nearest,
+			);
+			(image, *duration)
+		});
+		match format
+		{
+			RecordingFormat::Gif => write_gif(writer, images),
+			RecordingFormat::Apng => write_apng(writer, images, self.frames.len() as u32),
+		}
+		.map_err(|err| format!("failed to write {}: {}", path.display(), err))
+	}
+}
+
+/// GIF delays are in centiseconds, so each delay is rounded from the running total to keep clips from drifting
+fn write_gif(writer: BufWriter<File>, images: impl Iterator<Item = (RgbaImage, u32)>) -> Result<(), String>
+{
+	let mut encoder = GifEncoder::new(writer);
+	encoder.set_repeat(Repeat::Infinite).map_err(|err| err.to_string())?;
+	let centiseconds = |frames: u32| (frames * 100 + FRAME_RATE / 2) / FRAME_RATE;
+	let mut elapsed = 0;
+	for (image, duration) in images
+	{
+		let delay = centiseconds(elapsed + duration) - centiseconds(elapsed);
+		elapsed += duration;
+		let frame = Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay * 10, 1));
+		encoder.encode_frame(frame).map_err(|err| err.to_string())?;
+	}
+	Ok(())
+}
+
+/// APNG delays are fractions, so frames last exactly `duration / 60` seconds
+fn write_apng(
+	writer: BufWriter<File>,
+	mut images: impl Iterator<Item = (RgbaImage, u32)>,
+	frame_count: u32,
+) -> Result<(), String>
+{
+	let Some((first, duration)) = images.next()
+	else
+	{
+		return Ok(());
+	};
+	let mut encoder = png::Encoder::new(writer, first.width(), first.height());
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_animated(frame_count, 0).map_err(|err| err.to_string())?;
+	let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+	for (image, duration) in std::iter::once((first, duration)).chain(images)
+	{
+		let duration = duration.min(u16::MAX as u32) as u16;
+		writer
+			.set_frame_delay(duration, FRAME_RATE as u16)
+			.map_err(|err| err.to_string())?;
+		writer.write_image_data(&image).map_err(|err| err.to_string())?;
+	}
+	writer.finish().map_err(|err| err.to_string())
+}
+
+/// Records the display while toggled on, saving the clip to the working directory when toggled off
+#[derive(Resource, Default)]
+pub struct Recorder
+{
+	pub format: RecordingFormat,
+	pub recording: Option<Recording>,
+}
+
+impl Recorder
+{
+	fn output_path(&self, rom: Option<&Chip8Rom>) -> PathBuf
+	{
+		PathBuf::from(format!("{}.{}", capture_stem(rom), self.format.extension()))
+	}
+}
+
+pub fn recorder_input(
+	key: Res<ButtonInput<KeyCode>>,
+	hotkeys: Res<Hotkeys>,
+	settings: Res<Chip8Settings>,
+	rom: Option<Res<Chip8Rom>>,
+	mut recorder: ResMut<Recorder>,
+)
+{
+	if !key.just_pressed(hotkeys.record)
+	{
+		return;
+	}
+	match recorder.recording.take()
+	{
+		None =>
+		{
+			info!("Recording started");
+			recorder.recording = Some(Recording::default());
+		}
+		Some(recording) =>
+		{
+			let path = recorder.output_path(rom.as_deref());
+			match recording.save(&path, recorder.format, settings.palette)
+			{
+				Ok(()) => info!("Saved {} frames to {}", recording.len(), path.display()),
+				Err(err) => error!("Recording not saved: {}", err),
+			}
+		}
+	}
+}
+
+/// Runs on the fixed 60Hz schedule so every emulated frame is captured once, regardless of the refresh rate
+pub fn capture_frame(cpu: Res<Chip8CPU>, mut recorder: ResMut<Recorder>)
+{
+	if let Some(recording) = &mut recorder.recording
+	{
+		recording.capture(&cpu.0);
+	}
+}
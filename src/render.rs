@@ -0,0 +1,39 @@
+use bevy::color::LinearRgba;
+#[cfg(feature = "tracing")]
+use bevy::log::info_span;
+use chip8_core::{
+	Framebuffer,
+	chip8::{DISPLAY_HEIGHT_HIGHRES, DISPLAY_WIDTH_HIGHRES},
+};
+use image::ImageBuffer;
+
+/// Draws the display at 128x64 in the two palette colors, low resolution pixels as 2x2 blocks
+pub fn render_image(
+	display: &Framebuffer,
+	color1: LinearRgba,
+	color2: LinearRgba,
+) -> ImageBuffer<image::Rgba<u8>, Vec<u8>>
+{
+	#[cfg(feature = "tracing")]
+	let _ = info_span!("Render Image").entered();
+	let mut image = ImageBuffer::new(DISPLAY_WIDTH_HIGHRES as u32, DISPLAY_HEIGHT_HIGHRES as u32);
+	write_image(display, color1, color2, &mut image);
+	image
+}
+
+/// Draws the display into existing 128x64 RGBA bytes, so a texture can be updated without allocating
+pub fn write_image(display: &Framebuffer, color1: LinearRgba, color2: LinearRgba, out: &mut [u8])
+{
+	let colors = [to_pixel(&color1).0, to_pixel(&color2).0];
+	display.write_rgba(&colors, DISPLAY_WIDTH_HIGHRES / display.width(), out);
+}
+
+fn to_pixel(col: &LinearRgba) -> image::Rgba<u8>
+{
+	image::Rgba([
+		(col.red * 255.0) as u8,
+		(col.green * 255.0) as u8,
+		(col.blue * 255.0) as u8,
+		255,
+	])
+}
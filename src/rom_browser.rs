@@ -0,0 +1,261 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{chip8_display::LoadRom, controls::Hotkeys};
+
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+const MAX_RECENT: usize = 10;
+const VISIBLE_ROWS: usize = 20;
+
+const PANEL_COLOR: Color = Color::srgba(0.05, 0.0, 0.03, 0.92);
+const HEADER_COLOR: Color = Color::srgb(1., 0., 0.4);
+const SELECTED_COLOR: Color = Color::srgb(0.35, 0., 0.15);
+
+/// Lists ROMs from the configured directory along with recently opened files
+#[derive(Resource)]
+pub struct RomBrowser
+{
+	pub open: bool,
+	pub dir: PathBuf,
+	pub recent: Vec<PathBuf>,
+	pub files: Vec<PathBuf>,
+	pub selected: usize,
+}
+
+impl RomBrowser
+{
+	pub fn new(dir: PathBuf) -> Self
+	{
+		let mut browser = Self {
+			open: false,
+			dir,
+			recent: load_recent(),
+			files: Vec::new(),
+			selected: 0,
+		};
+		browser.refresh();
+		browser
+	}
+
+	/// Rescans the ROM directory
+	pub fn refresh(&mut self)
+	{
+		self.files = fs::read_dir(&self.dir)
+			.map(|entries| {
+				entries
+					.filter_map(|e| e.ok().map(|e| e.path()))
+					.filter(|p| is_rom(p))
+					.collect()
+			})
+			.unwrap_or_default();
+		self.files.sort();
+		self.selected = self.selected.min(self.len().saturating_sub(1));
+	}
+
+	fn len(&self) -> usize
+	{
+		self.recent.len() + self.files.len()
+	}
+
+	fn get(&self, index: usize) -> Option<&PathBuf>
+	{
+		self.recent.iter().chain(self.files.iter()).nth(index)
+	}
+
+	/// Moves `path` to the front of the recent list and saves it
+	pub fn add_recent(&mut self, path: &Path)
+	{
+		let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+		self.recent.retain(|p| *p != path);
+		self.recent.insert(0, path);
+		self.recent.truncate(MAX_RECENT);
+		save_recent(&self.recent);
+	}
+}
+
+fn is_rom(path: &Path) -> bool
+{
+	path.extension()
+		.and_then(|ext| ext.to_str())
+		.is_some_and(|ext| ROM_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+fn recent_path() -> Option<PathBuf>
+{
+	dirs::config_dir().map(|dir| dir.join("chip-8").join("recent.json"))
+}
+
+fn load_recent() -> Vec<PathBuf>
+{
+	recent_path()
+		.and_then(|path| fs::read_to_string(path).ok())
+		.and_then(|text| serde_json::from_str(&text).ok())
+		.unwrap_or_default()
+}
+
+fn save_recent(recent: &[PathBuf])
+{
+	let Some(path) = recent_path()
+	else
+	{
+		return;
+	};
+	let result = path
+		.parent()
+		.map_or(Ok(()), fs::create_dir_all)
+		.and_then(|_| fs::write(&path, serde_json::to_string_pretty(recent).unwrap_or_default()));
+	if let Err(err) = result
+	{
+		warn!("Failed to save recent files to {}: {}", path.display(), err);
+	}
+}
+
+#[derive(Component)]
+pub struct RomBrowserPanel;
+
+#[derive(Component)]
+pub struct RomEntry(usize);
+
+pub fn browser_input(
+	key: Res<ButtonInput<KeyCode>>,
+	hotkeys: Res<Hotkeys>,
+	mut browser: ResMut<RomBrowser>,
+	mut load: MessageWriter<LoadRom>,
+)
+{
+	if key.just_pressed(hotkeys.rom_browser)
+	{
+		browser.open = !browser.open;
+		if browser.open
+		{
+			browser.refresh();
+		}
+	}
+	if !browser.open || browser.len() == 0
+	{
+		return;
+	}
+	if key.just_pressed(KeyCode::ArrowDown)
+	{
+		browser.selected = (browser.selected + 1) % browser.len();
+	}
+	if key.just_pressed(KeyCode::ArrowUp)
+	{
+		browser.selected = browser.selected.checked_sub(1).unwrap_or(browser.len() - 1);
+	}
+	if key.just_pressed(KeyCode::Enter)
+		&& let Some(path) = browser.get(browser.selected)
+	{
+		load.write(LoadRom(path.clone()));
+	}
+}
+
+pub fn browser_click(
+	entries: Query<(&Interaction, &RomEntry), Changed<Interaction>>,
+	browser: Res<RomBrowser>,
+	mut load: MessageWriter<LoadRom>,
+)
+{
+	for (interaction, entry) in &entries
+	{
+		if *interaction == Interaction::Pressed
+			&& let Some(path) = browser.get(entry.0)
+		{
+			load.write(LoadRom(path.clone()));
+		}
+	}
+}
+
+pub fn file_drop(mut drops: MessageReader<FileDragAndDrop>, mut load: MessageWriter<LoadRom>)
+{
+	for drop in drops.read()
+	{
+		if let FileDragAndDrop::DroppedFile { path_buf, .. } = drop
+		{
+			load.write(LoadRom(path_buf.clone()));
+		}
+	}
+}
+
+/// Rebuilds the panel whenever the browser changes, the list is small enough that diffing isn't worth it
+pub fn draw_browser(mut commands: Commands, browser: Res<RomBrowser>, panels: Query<Entity, With<RomBrowserPanel>>)
+{
+	if !browser.is_changed()
+	{
+		return;
+	}
+	for panel in &panels
+	{
+		commands.entity(panel).despawn();
+	}
+	if !browser.open
+	{
+		return;
+	}
+
+	let first = browser.selected.saturating_sub(VISIBLE_ROWS / 2);
+	let last = (first + VISIBLE_ROWS).min(browser.len());
+	commands
+		.spawn((
+			RomBrowserPanel,
+			Node {
+				position_type: PositionType::Absolute,
+				width: percent(100),
+				height: percent(100),
+				flex_direction: FlexDirection::Column,
+				padding: UiRect::all(px(16)),
+				..default()
+			},
+			BackgroundColor(PANEL_COLOR),
+			GlobalZIndex(10),
+		))
+		.with_children(|panel| {
+			if browser.len() == 0
+			{
+				panel.spawn(Text::new(format!(
+					"No ROMs found in {}, drop a file onto the window to load it",
+					browser.dir.display()
+				)));
+				return;
+			}
+			for index in first..last
+			{
+				if index == 0 && !browser.recent.is_empty()
+				{
+					panel.spawn((Text::new("Recent"), TextColor(HEADER_COLOR)));
+				}
+				if index == browser.recent.len()
+				{
+					panel.spawn((
+						Text::new(format!("ROMs in {}", browser.dir.display())),
+						TextColor(HEADER_COLOR),
+					));
+				}
+				let path = browser.get(index).expect("index is within the list");
+				let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+				let background = if index == browser.selected
+				{
+					SELECTED_COLOR
+				}
+				else
+				{
+					Color::NONE
+				};
+				panel
+					.spawn((
+						Button,
+						RomEntry(index),
+						Node {
+							padding: UiRect::horizontal(px(8)),
+							..default()
+						},
+						BackgroundColor(background),
+					))
+					.with_child(Text::new(name));
+			}
+		});
+}
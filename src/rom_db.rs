@@ -0,0 +1,305 @@
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+use bevy::prelude::*;
+use chip8_core::{
+	memory::MAX_MEMORY,
+	palette::parse_hex,
+	quirks::{Platform, Quirks},
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use sha1::{Digest, Sha1};
+
+use crate::settings::{Chip8Settings, Palette};
+
+/// A ROM database in the format of the community CHIP-8 database (https://github.com/chip-8/chip-8-database).
+/// Only its platforms are bundled in `assets/chip8-database`, so ROMs are recognised once the full database is copied
+/// there or `--rom-db` points at a checkout of it.
+#[derive(Resource, Default, Clone)]
+pub struct RomDatabase
+{
+	hashes: HashMap<String, usize>,
+	programs: Vec<Program>,
+	platforms: Vec<PlatformEntry>,
+}
+
+#[derive(Deserialize, Clone)]
+struct Program
+{
+	title: String,
+	#[serde(default)]
+	authors: Vec<String>,
+	#[serde(default)]
+	roms: HashMap<String, RomEntry>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+struct RomEntry
+{
+	platforms: Vec<String>,
+	quirky_platforms: HashMap<String, QuirkOverrides>,
+	tickrate: Option<usize>,
+	start_address: Option<usize>,
+	colors: Option<Colors>,
+	keys: HashMap<String, u8>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+struct Colors
+{
+	pixels: Vec<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PlatformEntry
+{
+	id: String,
+	default_tickrate: Option<usize>,
+	quirks: QuirkOverrides,
+}
+
+/// Quirks as named by the database. Those this emulator has no switch for are ignored.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+struct QuirkOverrides
+{
+	shift: Option<bool>,
+	memory_leave_i_unchanged: Option<bool>,
+	jump: Option<bool>,
+	logic: Option<bool>,
+	wrap: Option<bool>,
+	vblank: Option<bool>,
+}
+
+impl QuirkOverrides
+{
+	fn apply(&self, quirks: &mut Quirks)
+	{
+		if let Some(shift) = self.shift
+		{
+			quirks.shift_vx = shift;
+		}
+		if let Some(leave_i) = self.memory_leave_i_unchanged
+		{
+			quirks.memory_increment_i = !leave_i;
+		}
+		if let Some(jump) = self.jump
+		{
+			quirks.jump_vx = jump;
+		}
+		if let Some(logic) = self.logic
+		{
+			quirks.logic_reset_vf = logic;
+		}
+		if let Some(wrap) = self.wrap
+		{
+			quirks.wrap_sprites = wrap;
+		}
+		if let Some(vblank) = self.vblank
+		{
+			quirks.display_wait = vblank;
+		}
+	}
+}
+
+/// Database ids of the platforms this emulator can run
+fn supported_platform(id: &str) -> Option<Platform>
+{
+	match id
+	{
+		"originalChip8" | "hybridVIP" | "modernChip8" => Some(Platform::Chip8),
+		"chip48" | "superchip1" | "superchip" => Some(Platform::SuperChip),
+		_ => None,
+	}
+}
+
+/// What the database knows about a ROM, resolved to settings this emulator understands
+#[derive(Clone, Debug, Default)]
+pub struct RomInfo
+{
+	pub title: String,
+	pub authors: Vec<String>,
+	pub platform: Option<Platform>,
+	pub quirks: Option<Quirks>,
+	pub tickrate: Option<usize>,
+	pub start_address: Option<usize>,
+	pub palette: Option<Palette>,
+	/// Keypad keys the ROM uses for directions and buttons, e.g. `("up", 0x5)`
+	pub keys: Vec<(String, u8)>,
+}
+
+impl RomInfo
+{
+	/// Title and authors as shown in the window title
+	pub fn display_name(&self) -> String
+	{
+		if self.authors.is_empty()
+		{
+			self.title.clone()
+		}
+		else
+		{
+			format!("{} by {}", self.title, self.authors.join(", "))
+		}
+	}
+
+	/// Applies the database settings, keeping anything the user chose explicitly
+	pub fn apply(&self, settings: &mut Chip8Settings)
+	{
+		if !settings.overrides.platform
+		{
+			if let Some(platform) = self.platform
+			{
+				settings.platform = platform;
+			}
+			if let Some(quirks) = self.quirks
+			{
+				let chosen = settings.quirks;
+				settings.quirks = quirks;
+				if settings.overrides.wrap_sprites
+				{
+					settings.quirks.wrap_sprites = chosen.wrap_sprites;
+				}
+				if settings.overrides.display_wait
+				{
+					settings.quirks.display_wait = chosen.display_wait;
+				}
+			}
+		}
+		if !settings.overrides.speed
+			&& let Some(tickrate) = self.tickrate
+		{
+			settings.ticks_per_frame = tickrate;
+		}
+		if !settings.overrides.palette
+			&& let Some(palette) = self.palette
+		{
+			settings.palette = palette;
+		}
+		if !settings.overrides.start_address
+			&& let Some(start_address) = self.start_address
+		{
+			settings.memory.start_address = start_address;
+		}
+	}
+}
+
+pub fn sha1_hex(bytes: &[u8]) -> String
+{
+	Sha1::digest(bytes)
+		.iter()
+		.fold(String::with_capacity(40), |mut hex, b| {
+			let _ = write!(hex, "{:02x}", b);
+			hex
+		})
+}
+
+impl RomDatabase
+{
+	pub fn bundled() -> Self
+	{
+		Self::parse(
+			include_str!("../assets/chip8-database/sha1-hashes.json"),
+			include_str!("../assets/chip8-database/programs.json"),
+			include_str!("../assets/chip8-database/platforms.json"),
+		)
+		.expect("Bundled ROM database is invalid")
+	}
+
+	/// Whether no ROMs can be recognised, as with only the bundled platforms
+	pub fn is_empty(&self) -> bool
+	{
+		self.programs.is_empty()
+	}
+
+	/// Loads `sha1-hashes.json`, `programs.json` and `platforms.json` from a directory
+	pub fn load_dir(dir: &Path) -> Result<Self, String>
+	{
+		let read = |name: &str| {
+			let path = dir.join(name);
+			fs::read_to_string(&path).map_err(|err| format!("failed to read {}: {}", path.display(), err))
+		};
+		Self::parse(
+			&read("sha1-hashes.json")?,
+			&read("programs.json")?,
+			&read("platforms.json")?,
+		)
+		.map_err(|err| format!("invalid ROM database in {}: {}", dir.display(), err))
+	}
+
+	pub(crate) fn parse(hashes: &str, programs: &str, platforms: &str) -> Result<Self, String>
+	{
+		fn parse<T: DeserializeOwned>(name: &str, json: &str) -> Result<T, String>
+		{
+			serde_json::from_str(json).map_err(|err| format!("{}: {}", name, err))
+		}
+		Ok(Self {
+			hashes: parse("sha1-hashes.json", hashes)?,
+			programs: parse("programs.json", programs)?,
+			platforms: parse("platforms.json", platforms)?,
+		})
+	}
+
+	pub fn lookup(&self, rom: &[u8]) -> Option<RomInfo>
+	{
+		let hash = sha1_hex(rom);
+		let program = self.programs.get(*self.hashes.get(&hash)?)?;
+		let mut info = RomInfo {
+			title: program.title.clone(),
+			authors: program.authors.clone(),
+			..default()
+		};
+		let Some(entry) = program.roms.get(&hash)
+		else
+		{
+			return Some(info);
+		};
+
+		// Use the first platform listed that can be emulated, the database lists the preferred one first
+		let platform = entry.platforms.iter().find_map(|id| {
+			Some((
+				id,
+				supported_platform(id)?,
+				self.platforms.iter().find(|p| p.id == *id)?,
+			))
+		});
+		match platform
+		{
+			Some((id, platform, db_platform)) =>
+			{
+				let mut quirks = Quirks::for_platform(platform);
+				db_platform.quirks.apply(&mut quirks);
+				if let Some(overrides) = entry.quirky_platforms.get(id)
+				{
+					overrides.apply(&mut quirks);
+				}
+				info.platform = Some(platform);
+				info.quirks = Some(quirks);
+				info.tickrate = entry.tickrate.or(db_platform.default_tickrate);
+			}
+			None if !entry.platforms.is_empty() =>
+			{
+				warn!(
+					"{} targets unsupported platforms: {}",
+					program.title,
+					entry.platforms.join(", ")
+				);
+			}
+			None => (),
+		}
+		info.start_address = entry.start_address.filter(|addr| *addr < MAX_MEMORY);
+		info.palette = entry.colors.as_ref().and_then(|colors| match colors.pixels.as_slice()
+		{
+			[background, foreground, ..] => Some(Palette::from_rgb(
+				parse_hex(background).ok()?,
+				parse_hex(foreground).ok()?,
+			)),
+			_ => None,
+		});
+		info.keys = entry.keys.iter().map(|(name, key)| (name.clone(), *key)).collect();
+		info.keys.sort();
+		Some(info)
+	}
+}
@@ -0,0 +1,96 @@
+use std::{
+	path::Path,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use chip8_core::chip8::{Chip8, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use image::{
+	RgbaImage,
+	imageops::{self, FilterType},
+};
+
+use crate::{
+	chip8_display::{Chip8CPU, Chip8Rom},
+	controls::Hotkeys,
+	render::render_image,
+	settings::{Chip8Settings, Palette},
+};
+
+/// Size of the scaled screenshot, in output pixels per low resolution display pixel
+#[derive(Resource)]
+pub struct ScreenshotScale(pub u32);
+
+/// Names captures after the loaded ROM and the time in milliseconds, so repeated captures don't overwrite each other
+pub fn capture_stem(rom: Option<&Chip8Rom>) -> String
+{
+	let name = rom
+		.and_then(|rom| Path::new(&rom.path).file_stem()?.to_str().map(String::from))
+		.unwrap_or_else(|| "chip8".into());
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|time| time.as_millis())
+		.unwrap_or_default();
+	format!("{}-{}", name, timestamp)
+}
+
+/// The display at its own resolution, 64x32 or 128x64
+pub fn native_image(cpu: &Chip8, palette: Palette) -> RgbaImage
+{
+	let image = render_image(&cpu.display, palette.background, palette.foreground);
+	if cpu.high_res()
+	{
+		image
+	}
+	else
+	{
+		// render_image draws low resolution pixels as 2x2 blocks
+		imageops::resize(&image, DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32, FilterType::Nearest)
+	}
+}
+
+/// The display scaled so low and high resolution screenshots come out the same size
+pub fn scaled_image(cpu: &Chip8, palette: Palette, scale: u32) -> RgbaImage
+{
+	let image = native_image(cpu, palette);
+	let scale = if cpu.high_res()
+	{
+		(scale / 2).max(1)
+	}
+	else
+	{
+		scale.max(1)
+	};
+	imageops::resize(
+		&image,
+		image.width() * scale,
+		image.height() * scale,
+		FilterType::Nearest,
+	)
+}
+
+pub fn screenshot_input(
+	key: Res<ButtonInput<KeyCode>>,
+	hotkeys: Res<Hotkeys>,
+	scale: Res<ScreenshotScale>,
+	settings: Res<Chip8Settings>,
+	cpu: Res<Chip8CPU>,
+	rom: Option<Res<Chip8Rom>>,
+)
+{
+	if !key.just_pressed(hotkeys.screenshot)
+	{
+		return;
+	}
+	let stem = capture_stem(rom.as_deref());
+	let native = format!("{}.png", stem);
+	let scaled = format!("{}-{}x.png", stem, scale.0);
+	let result = native_image(&cpu.0, settings.palette)
+		.save(&native)
+		.and_then(|_| scaled_image(&cpu.0, settings.palette, scale.0).save(&scaled));
+	match result
+	{
+		Ok(()) => info!("Saved screenshots {} and {}", native, scaled),
+		Err(err) => error!("Screenshot not saved: {}", err),
+	}
+}
@@ -0,0 +1,234 @@
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use bevy::prelude::*;
+use chip8_core::{
+	chip8::Chip8,
+	memory::MemoryProfile,
+	palette,
+	quirks::{Platform, Quirks},
+};
+use serde::Deserialize;
+
+use crate::{controls::Hotkeys, rom_db::RomInfo};
+
+pub const DEFAULT_TICKS_PER_FRAME: usize = 30;
+
+/// Everything needed to build a fresh machine for a ROM
+#[derive(Resource, Clone, Debug)]
+pub struct Chip8Settings
+{
+	pub platform: Platform,
+	pub quirks: Quirks,
+	/// Instructions executed per 60Hz frame
+	pub ticks_per_frame: usize,
+	/// RAM size and where the font and program go
+	pub memory: MemoryProfile,
+	pub seed: Option<u64>,
+	pub palette: Palette,
+	pub overrides: Overrides,
+}
+
+/// Settings the user chose explicitly, which take precedence over ROM database hints
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Overrides
+{
+	pub platform: bool,
+	pub speed: bool,
+	pub palette: bool,
+	pub start_address: bool,
+	pub wrap_sprites: bool,
+	pub display_wait: bool,
+}
+
+/// Settings from the command line, before any ROM database hints are applied
+#[derive(Resource, Clone, Debug)]
+pub struct BaseSettings(pub Chip8Settings);
+
+impl Default for Chip8Settings
+{
+	fn default() -> Self
+	{
+		Self {
+			platform: Platform::default(),
+			quirks: Quirks::default(),
+			ticks_per_frame: DEFAULT_TICKS_PER_FRAME,
+			memory: MemoryProfile::default(),
+			seed: None,
+			palette: Palette::default(),
+			overrides: Overrides::default(),
+		}
+	}
+}
+
+impl Chip8Settings
+{
+	/// Resolves the settings to run a ROM with, filling in whatever the database knows about it
+	pub fn for_rom(&self, info: Option<&RomInfo>) -> Self
+	{
+		let mut settings = self.clone();
+		if let Some(info) = info
+		{
+			info.apply(&mut settings);
+		}
+		settings
+	}
+
+	/// A machine running `rom`, or why the memory profile can't hold it
+	pub fn try_create_cpu(&self, rom: &[u8]) -> Result<Chip8, &'static str>
+	{
+		let mut cpu = Chip8::try_with_memory(self.memory)?;
+		cpu.quirks = self.quirks;
+		if let Some(seed) = self.seed
+		{
+			cpu.seed_rng(seed);
+		}
+		cpu.try_load_code(rom)?;
+		cpu.need_draw = true;
+		Ok(cpu)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette
+{
+	pub background: LinearRgba,
+	pub foreground: LinearRgba,
+}
+
+impl Palette
+{
+	pub const fn from_rgb(background: [u8; 3], foreground: [u8; 3]) -> Self
+	{
+		Self {
+			background: Self::color(background),
+			foreground: Self::color(foreground),
+		}
+	}
+
+	const fn color(rgb: [u8; 3]) -> LinearRgba
+	{
+		LinearRgba::rgb(rgb[0] as f32 / 255., rgb[1] as f32 / 255., rgb[2] as f32 / 255.)
+	}
+
+	/// The background as a clear color, matching how the display texture is interpreted
+	pub fn clear_color(&self) -> ClearColor
+	{
+		ClearColor(Color::srgb(
+			self.background.red,
+			self.background.green,
+			self.background.blue,
+		))
+	}
+}
+
+impl Default for Palette
+{
+	fn default() -> Self
+	{
+		let [background, foreground] = palette::PRESETS[0].1;
+		Self::from_rgb(background, foreground)
+	}
+}
+
+impl FromStr for Palette
+{
+	type Err = String;
+
+	/// Accepts either a preset name or a `background,foreground` pair of hex colors
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		let [background, foreground] = palette::parse(s)?;
+		Ok(Self::from_rgb(background, foreground))
+	}
+}
+
+/// Physical keys bound to each of the 16 keypad keys, indexed by keypad value
+#[derive(Resource, Clone, Debug)]
+pub struct Keymap(pub [KeyCode; 16]);
+
+impl Default for Keymap
+{
+	fn default() -> Self
+	{
+		Self([
+			KeyCode::KeyX,
+			KeyCode::Digit1,
+			KeyCode::Digit2,
+			KeyCode::Digit3,
+			KeyCode::KeyQ,
+			KeyCode::KeyW,
+			KeyCode::KeyE,
+			KeyCode::KeyA,
+			KeyCode::KeyS,
+			KeyCode::KeyD,
+			KeyCode::KeyZ,
+			KeyCode::KeyC,
+			KeyCode::Digit4,
+			KeyCode::KeyR,
+			KeyCode::KeyF,
+			KeyCode::KeyV,
+		])
+	}
+}
+
+/// Extra bindings for the controls a ROM declares in the database, so arrow keys and space just work
+#[derive(Resource, Clone, Debug, Default)]
+pub struct KeyHints(pub Vec<(KeyCode, usize)>);
+
+impl KeyHints
+{
+	pub fn from_info(info: Option<&RomInfo>) -> Self
+	{
+		let Some(info) = info
+		else
+		{
+			return Self::default();
+		};
+		let bindings = info
+			.keys
+			.iter()
+			.filter(|(_, key)| *key < 16)
+			.filter_map(|(name, key)| {
+				let code = match name.as_str()
+				{
+					"up" => KeyCode::ArrowUp,
+					"down" => KeyCode::ArrowDown,
+					"left" => KeyCode::ArrowLeft,
+					"right" => KeyCode::ArrowRight,
+					"a" => KeyCode::Space,
+					"b" => KeyCode::ShiftLeft,
+					_ => return None,
+				};
+				Some((code, *key as usize))
+			})
+			.collect();
+		Self(bindings)
+	}
+}
+
+/// On-disk keymap, e.g. `{ "keypad": { "5": "ArrowUp" }, "hotkeys": { "pause": "Space" } }`.
+/// Keypad keys are hex digits and anything left out keeps its default binding.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct KeymapFile
+{
+	keypad: HashMap<String, KeyCode>,
+	hotkeys: Option<Hotkeys>,
+}
+
+pub fn load_keymap(path: &Path) -> Result<(Keymap, Hotkeys), String>
+{
+	let text = fs::read_to_string(path).map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+	let file: KeymapFile =
+		serde_json::from_str(&text).map_err(|err| format!("invalid keymap {}: {}", path.display(), err))?;
+	let mut keymap = Keymap::default();
+	for (key, code) in file.keypad
+	{
+		let index = u8::from_str_radix(&key, 16)
+			.ok()
+			.filter(|k| *k < 16)
+			.ok_or_else(|| format!("invalid keypad key '{}' in {}, expected 0-F", key, path.display()))?;
+		keymap.0[index as usize] = code;
+	}
+	Ok((keymap, file.hotkeys.unwrap_or_default()))
+}
@@ -1,569 +1,208 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests
 {
+	use chip8_core::{
+		Framebuffer,
+		chip8::{Chip8, DISPLAY_HEIGHT_HIGHRES, DISPLAY_WIDTH_HIGHRES},
+		quirks::{Platform, Quirks},
+	};
+	use clap::Parser;
 
-	use crate::chip8::Chip8;
+	use crate::{
+		cli::Args,
+		movie::Movie,
+		recorder::{Recording, RecordingFormat},
+		render::render_image,
+		rom_db::RomDatabase,
+		screenshot::{native_image, scaled_image},
+		settings::{Chip8Settings, Palette},
+	};
 
-	#[test]
-	fn jump()
-	{
-		let mut emu = Chip8::new();
-
-		emu.load_code(vec![0x13, 0x45]).tick();
-
-		assert_eq!(emu.program_counter, 0x345);
-	}
-
-	#[test]
-	fn call()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x23, 0x55]).tick();
-
-		assert_eq!(emu.program_counter, 0x355);
-		assert_eq!(emu.stack_pointer, 1);
-		assert_eq!(emu.stack[emu.stack_pointer], 0x200);
-	}
-
-	#[test]
-	fn ret()
-	{
-		let mut emu = Chip8::new();
-
-		emu.load_code(vec![0x22, 0x04, 0x00, 0xE0, 0x00, 0xEE]);
-		emu.tick();
-		emu.tick();
-
-		assert_eq!(emu.program_counter, 0x200 + 2, "Did not return to the correct addr");
-		assert_eq!(emu.stack_pointer, 0, "Stack pointer is incorrect");
-	}
-
-	#[test]
-	fn skip_eq()
-	{
-		let mut emu = Chip8::new();
-		emu.registers[0x3] = 0x33;
-
-		//Skip
-		emu.load_code(vec![0x33, 0x33]);
-		emu.tick();
-		assert_eq!(emu.program_counter, 0x200 + 4);
-
-		//Dont Skip
-		emu.load_code(vec![0x33, 0x35]).tick();
-		assert_eq!(emu.program_counter, 0x200 + 2);
-	}
-
-	#[test]
-	fn skip_ne()
-	{
-		let mut emu = Chip8::new();
-		emu.registers[0x3] = 0x33;
-
-		//Dont Skip
-		emu.load_code(vec![0x43, 0x33]);
-		emu.tick();
-		assert_eq!(emu.program_counter, 0x200 + 2);
-
-		//Skip
-		emu.load_code(vec![0x43, 0x35]).tick();
-		assert_eq!(emu.program_counter, 0x200 + 4);
-	}
-
-	#[test]
-	fn skip_eq2()
-	{
-		let mut emu = Chip8::new();
-		emu.registers[0x3] = 0x33;
-		emu.registers[0x4] = 0x33;
-
-		//Skip
-		emu.load_code(vec![0x53, 0x40]);
-		emu.tick();
-		assert_eq!(emu.program_counter, 0x200 + 4);
-
-		//Dont Skip
-		emu.load_code(vec![0x53, 0x20]).tick();
-		assert_eq!(emu.program_counter, 0x200 + 2);
-	}
-
-	#[test]
-	fn set_register()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x63, 0x40]).tick();
-
-		assert_eq!(emu.registers[0x3], 0x40);
-	}
-
-	#[test]
-	fn add()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x72, 0x70]);
-		emu.registers[0x2] = 0x01;
-		emu.tick();
-
-		assert_eq!(emu.registers[0x2], 0x01 + 0x70);
-	}
+	/// The ROMs described by `tests/fixtures/rom-db`, with the bundled platforms
+	const LOOP: [u8; 2] = [0x12, 0x00];
+	const CLEAR: [u8; 4] = [0x00, 0xE0, 0x12, 0x02];
+	const XO_ONLY: [u8; 4] = [0x12, 0x02, 0x12, 0x02];
 
-	#[test]
-	fn math_copy()
+	fn fixture_db() -> RomDatabase
 	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x70]);
-		emu.registers[0x2] = 0x10;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		assert_eq!(emu.registers[0x7], 0x33, "Original Value Modified");
-		assert_eq!(emu.registers[0x2], 0x33, "Value not copied");
+		RomDatabase::parse(
+			include_str!("../tests/fixtures/rom-db/sha1-hashes.json"),
+			include_str!("../tests/fixtures/rom-db/programs.json"),
+			include_str!("../assets/chip8-database/platforms.json"),
+		)
+		.unwrap()
 	}
 
 	#[test]
-	fn math_bit_or()
+	fn render_high_res_edges()
 	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x71]);
-		emu.registers[0x2] = 0x10;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x10 | 0x33);
+		let mut display = Framebuffer::new(DISPLAY_WIDTH_HIGHRES, DISPLAY_HEIGHT_HIGHRES, 1);
+		display.set_pixel(0, 0, 1);
+		display.set_pixel(127, 0, 1);
+		let palette = Palette::default();
+		let image = render_image(&display, palette.background, palette.foreground);
+		let on = image.get_pixel(0, 0);
+		assert_eq!(image.get_pixel(127, 0), on, "Last column should be drawn");
+		assert_ne!(image.get_pixel(1, 0), on);
 	}
 
 	#[test]
-	fn math_bit_and()
+	fn recording_merges_repeated_frames()
 	{
 		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x72]);
-		emu.registers[0x2] = 0x10;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x10 & 0x33);
+		let mut recording = Recording::default();
+		recording.capture(&emu);
+		recording.capture(&emu);
+		emu.display.set_row(0, 3, 0xFF);
+		recording.capture(&emu);
+		assert_eq!(recording.len(), 3);
+		assert_eq!(recording.unique_frames(), 2);
 	}
 
 	#[test]
-	fn math_bit_xor()
+	fn recording_saves_gif_and_apng()
 	{
 		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x73]);
-		emu.registers[0x2] = 0x10;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x10 ^ 0x33);
-	}
-
-	#[test]
-	fn math_bit_add()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x74]);
-		emu.registers[0x2] = 0x10;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x10 + 0x33);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	fn math_bit_add_vf_as_vx()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x8F, 0x74]);
-		emu.registers[0xF] = 0x10;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		assert_eq!(emu.registers[0x7], 0x33);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	fn math_bit_add_vf_as_vy()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x87, 0xF4]);
-		emu.registers[0xF] = 0x10;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		assert_eq!(emu.registers[0x7], 0x33 + 0x10);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	fn math_bit_add_carry()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x74]);
-		emu.registers[0x2] = 0xF0;
-		emu.registers[0x7] = 0x33;
-		emu.tick();
-		let r: u16 = (0xF0 + 0x33) & 0x00FF;
-		assert_eq!(emu.registers[0x2], r as u8);
-		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
-	}
-
-	#[test]
-	fn math_bit_sub()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x75]);
-		emu.registers[0x2] = 0x4B;
-		emu.registers[0x7] = 0x2D;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x1E);
-		assert_eq!(emu.registers[0xF], 0x01, "VF incorrectly set");
-	}
-
-	#[test]
-	fn math_bit_sub_borrow()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x75]);
-		emu.registers[0x2] = 0x2D;
-		emu.registers[0x7] = 0x4B;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0xE2);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	#[cfg(feature = "schip")]
-	fn math_bit_shr()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x06]);
-		emu.registers[0x2] = 0x10;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x10 >> 1);
-		assert_eq!(emu.registers[0xF], 0);
-	}
-
-	#[test]
-	#[cfg(feature = "schip")]
-	fn math_bit_shr2()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x06]);
-		emu.registers[0x2] = 0x11;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x11 >> 1);
-		assert_eq!(emu.registers[0xF], 1);
-	}
-
-	#[test]
-	#[cfg(all(feature = "chip8", not(feature = "schip")))]
-	fn math_bit_shr()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x16]);
-		emu.registers[0x1] = 0x10;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x10 >> 1);
-		assert_eq!(emu.registers[0xF], 0);
-	}
-
-	#[test]
-	#[cfg(all(feature = "chip8", not(feature = "schip")))]
-	fn math_bit_shr2()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x16]);
-		emu.registers[0x1] = 0x11;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x11 >> 1);
-		assert_eq!(emu.registers[0xF], 1);
-	}
-
-	#[test]
-	fn math_bit_subn_borrow()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x77]);
-		emu.registers[0x2] = 0x4B;
-		emu.registers[0x7] = 0x2D;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0xE2);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-	#[test]
-	fn math_bit_subn()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x77]);
-		emu.registers[0x2] = 0x2D;
-		emu.registers[0x7] = 0x4B;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x1E);
-		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
-	}
-
-	#[test]
-	#[cfg(feature = "schip")]
-	fn math_bit_shl()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x0E]);
-		emu.registers[0x2] = 0x78;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x78 << 1);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	#[cfg(all(feature = "chip8", not(feature = "schip")))]
-	fn math_bit_shl()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x1E]);
-		emu.registers[0x1] = 0x78;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x78 << 1);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	#[cfg(feature = "schip")]
-	fn math_bit_shl2()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x0E]);
-		emu.registers[0x2] = 0x83;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x83 << 1);
-		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
-	}
-
-	#[test]
-	#[cfg(all(feature = "chip8", not(feature = "schip")))]
-	fn math_bit_shl2()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0x82, 0x1E]);
-		emu.registers[0x1] = 0x83;
-		emu.tick();
-		assert_eq!(emu.registers[0x2], 0x83 << 1);
-		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
-	}
-
-	#[test]
-	fn skip_ne2()
-	{
-		let mut emu = Chip8::new();
-		emu.registers[0x3] = 0x33;
-		emu.registers[0x4] = 0x33;
-
-		//Skip
-		emu.load_code(vec![0x93, 0x20]);
-		emu.tick();
-		assert_eq!(emu.program_counter, 0x200 + 4);
-
-		//Dont Skip
-		emu.load_code(vec![0x93, 0x40]).tick();
-		assert_eq!(emu.program_counter, 0x200 + 2);
-	}
-
-	#[test]
-	fn set_i()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xA3, 0x20]);
-		emu.tick();
-		assert_eq!(emu.reg_i, 0x320);
-	}
-
-	#[test]
-	#[cfg(all(feature = "chip8", not(feature = "schip")))]
-	fn jump_offset()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xB3, 0x20]);
-		emu.registers[0x0] = 0x4;
-		emu.tick();
-		assert_eq!(emu.program_counter, 0x320 + 0x4);
-	}
-
-	#[test]
-	#[cfg(feature = "schip")]
-	fn jump_offset()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xB3, 0x20]);
-		emu.registers[0x3] = 0x4;
-		emu.tick();
-		assert_eq!(emu.program_counter, 0x320 + 0x4);
-	}
-
-	#[test]
-	fn rand()
-	{
-		//todo replace with seeded rng
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xC3, 0x0f]);
-		emu.registers[0x3] = 0xff;
-		emu.tick();
-		assert_ne!(emu.registers[0x3], 0xff);
-	}
-
-	#[test]
-	fn draw_sprite()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xD3, 0x11]);
-		emu.registers[0x3] = 62;
-		//Draw a line at (62,0) clipping on the right
-		emu.tick();
-		let expected = 0x3 << 64;
-		println!("{:b}", expected);
-		println!("{:b}", emu.display[0]);
-		assert_eq!(emu.display[0], expected);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	fn draw_sprite_erase()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xD3, 0x11, 0xD4, 0x11]);
-		emu.registers[0x3] = 0;
-		emu.registers[0x4] = 2;
-		emu.tick();
-		emu.vblank();
-		emu.tick();
-		let expected = 0b110011 << 128 - 6;
-		println!("{:b}", expected);
-		println!("{:b}", emu.display[0]);
-		assert_eq!(emu.display[0], expected);
-		assert_eq!(emu.registers[0xF], 1, "VF incorrectly set");
-	}
-
-	#[test]
-	fn draw_sprite_highres()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xD3, 0x11]);
-		emu.registers[0x3] = 126;
-		emu.high_res = true;
-		//Draw a line at (62,0) clipping
-		emu.tick();
-		let expected = 0b11;
-		assert_eq!(emu.display[0], expected);
-		assert_eq!(emu.registers[0xF], 0, "VF incorrectly set");
-	}
-
-	#[test]
-	fn read_dt()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF3, 0x07]);
-		emu.reg_dt = 0x3;
-		emu.tick();
-		assert_eq!(emu.registers[0x3], 0x3);
-	}
-
-	#[test]
-	fn set_dt()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF3, 0x15]);
-		emu.registers[0x3] = 0x3;
-		emu.tick();
-		assert_eq!(emu.reg_dt, 0x3);
-	}
-
-	#[test]
-	fn set_st()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF3, 0x18]);
-		emu.registers[0x3] = 0x3;
-		emu.tick();
-		assert_eq!(emu.reg_st, 0x3);
-	}
-
-	#[test]
-	fn set_vi()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF3, 0x1E]);
-		emu.registers[0x3] = 0x3;
-		emu.tick();
-		assert_eq!(emu.reg_i, 0x3);
-	}
-
-	#[test]
-	fn set_vi_to_digit()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF3, 0x29]);
-		emu.registers[0x3] = 0x3;
-		emu.tick();
-		assert_eq!(emu.reg_i, 0x3 * 5);
-	}
-
-	#[test]
-	fn set_bcd()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF3, 0x33]);
-		emu.registers[0x3] = 128;
-		emu.reg_i = 0x300;
-		emu.tick();
-		assert_eq!(emu.ram[emu.reg_i as usize], 1, "Digit 1 is incorrect");
-		assert_eq!(emu.ram[emu.reg_i as usize + 1], 2, "Digit 2 is incorrect");
-		assert_eq!(emu.ram[emu.reg_i as usize + 2], 8, "Digit 3 is incorrect");
-	}
-
-	#[test]
-	fn store()
-	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF4, 0x55]);
-		for i in 0..0x4
+		let mut recording = Recording::default();
+		for i in 0..4
 		{
-			emu.registers[i] = i as u8;
+			emu.display.set_row(0, i, u128::MAX);
+			recording.capture(&emu);
 		}
-		emu.registers[0x5] = 0x9;
-		emu.reg_i = 0x300;
-		emu.tick();
-		for i in 0..0x4
+		for extension in ["gif", "png"]
 		{
-			assert_eq!(emu.ram[0x300 + i], i as u8);
+			let path = std::env::temp_dir().join(format!("chip8-recording-test.{}", extension));
+			let format = RecordingFormat::from_path(&path).unwrap();
+			recording.save(&path, format, Palette::default()).unwrap();
+			assert!(image::open(&path).is_ok(), "{} should decode", extension);
+			std::fs::remove_file(path).unwrap();
 		}
-		assert_eq!(emu.ram[emu.reg_i as usize + 0x5], 0, "Wrote too much");
-		assert_eq!(emu.reg_i, 0x300 + 0x4 + 1, "Register I was not incremented");
 	}
 
 	#[test]
-	fn read()
+	fn screenshot_sizes()
 	{
 		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF4, 0x65]);
-		emu.reg_i = 0x300;
-		for i in 0..0x4
-		{
-			emu.ram[emu.reg_i as usize + i] = i as u8;
-		}
-		emu.ram[emu.reg_i as usize + 0x5] = 0x9;
-		emu.tick();
-		for i in 0..0x3
-		{
-			assert_eq!(emu.registers[i], i as u8);
-		}
-		assert_eq!(emu.registers[0x5], 0, "Wrote too much");
-		assert_eq!(emu.reg_i, 0x300 + 0x4 + 1, "Register I was not incremented");
+		let palette = Palette::default();
+		assert_eq!(native_image(&emu, palette).dimensions(), (64, 32));
+		assert_eq!(scaled_image(&emu, palette, 10).dimensions(), (640, 320));
+		emu.set_high_res(true);
+		assert_eq!(native_image(&emu, palette).dimensions(), (128, 64));
+		assert_eq!(scaled_image(&emu, palette, 10).dimensions(), (640, 320));
 	}
 
 	#[test]
-	fn bcd_and_read()
+	fn movie_replays_exactly()
 	{
-		let mut emu = Chip8::new();
-		emu.load_code(vec![0xF2, 0x33, 0xF2, 0x65]);
-		emu.registers[0x2] = 128;
-		emu.reg_i = 0x300;
-		emu.tick();
-		emu.tick();
+		// Draws the pressed key's digit at a random height, so both input and the RNG affect the display
+		let rom = [0xF0, 0x0A, 0xC1, 0x1F, 0xF0, 0x29, 0xD0, 0x15, 0x12, 0x00];
+		let settings = Chip8Settings {
+			seed: Some(42),
+			..Default::default()
+		};
+		let mut movie = Movie::new(&rom, &settings);
+		let mut cpu = settings.try_create_cpu(&rom).unwrap();
+		for frame in 0..60
+		{
+			cpu.keys = std::array::from_fn(|key| frame % 7 == 0 && key == frame % 16);
+			movie.record(&cpu.keys);
+			cpu.run_frame(settings.ticks_per_frame);
+		}
 
-		assert_eq!(emu.registers[0x0], 1, "First Digit");
-		assert_eq!(emu.registers[0x1], 2, "Second Digit");
-		assert_eq!(emu.registers[0x2], 8, "Third Digit");
+		assert!(!cpu.display.is_blank(), "The ROM should have drawn something");
+
+		let movie: Movie = serde_json::from_str(&serde_json::to_string(&movie).unwrap()).unwrap();
+		assert!(movie.check_rom(&rom).is_ok());
+		let mut replay_settings = Chip8Settings::default();
+		movie.apply(&mut replay_settings);
+		let mut replay = replay_settings.try_create_cpu(&rom).unwrap();
+		for frame in 0..movie.frames.len()
+		{
+			replay.keys = movie.keys(frame);
+			replay.run_frame(replay_settings.ticks_per_frame);
+		}
+		assert_eq!(replay.display, cpu.display);
+		assert_eq!(replay.registers, cpu.registers);
+		assert_eq!(replay.program_counter, cpu.program_counter);
+	}
+
+	#[test]
+	fn rom_db_lookup()
+	{
+		let db = fixture_db();
+		assert!(!db.is_empty());
+		assert!(RomDatabase::bundled().is_empty(), "Only the platforms are bundled");
+		assert!(db.lookup(&[0x12, 0x04]).is_none(), "Unknown ROMs should not match");
+
+		let info = db.lookup(&LOOP).unwrap();
+		assert_eq!(info.display_name(), "Loop by Tester");
+		assert_eq!(info.platform, Some(Platform::SuperChip));
+		// The database's superchip quirks with the ROM's own shift and wrap overrides
+		let superchip = Quirks {
+			shift_vx: false,
+			jump_vx: true,
+			logic_reset_vf: false,
+			memory_increment_i: false,
+			wrap_sprites: true,
+			display_wait: false,
+		};
+		assert_eq!(info.quirks, Some(superchip));
+		assert_eq!(info.tickrate, Some(20));
+		assert_eq!(
+			info.palette,
+			Some(Palette::from_rgb([0x10, 0x20, 0x30], [0xA0, 0xB0, 0xC0]))
+		);
+		assert_eq!(info.keys, [("a".to_string(), 6), ("up".to_string(), 5)]);
+
+		// xochip can't be emulated, so the next platform listed is used with its default tickrate
+		let info = db.lookup(&CLEAR).unwrap();
+		assert_eq!(info.platform, Some(Platform::Chip8));
+		assert_eq!(info.quirks.map(|quirks| quirks.display_wait), Some(true));
+		assert_eq!(info.tickrate, Some(15));
+		assert_eq!(info.start_address, Some(0x600));
+		assert_eq!(info.palette, None);
+
+		let info = db.lookup(&XO_ONLY).unwrap();
+		assert_eq!(info.title, "Colors");
+		assert_eq!((info.platform, info.quirks, info.tickrate), (None, None, None));
+	}
+
+	#[test]
+	fn rom_db_hints_yield_to_command_line()
+	{
+		let info = fixture_db().lookup(&LOOP).unwrap();
+		let mut settings = Args::parse_from(["chip-8"]).resolve().settings;
+		info.apply(&mut settings);
+		assert_eq!(settings.platform, Platform::SuperChip);
+		assert_eq!(settings.quirks, info.quirks.unwrap());
+		assert_eq!(settings.ticks_per_frame, 20);
+		assert_eq!(settings.palette, info.palette.unwrap());
+
+		let args = Args::parse_from(["chip-8", "--platform", "chip8", "--speed", "50", "--palette", "amber"]);
+		let mut settings = args.resolve().settings;
+		let chosen = settings.clone();
+		info.apply(&mut settings);
+		assert_eq!(settings.platform, Platform::Chip8);
+		assert_eq!(settings.quirks, chosen.quirks);
+		assert_eq!(settings.ticks_per_frame, 50);
+		assert_eq!(settings.palette, chosen.palette);
+
+		// Quirk flags can force either value over the database
+		let args = Args::parse_from(["chip-8", "--wrap-sprites=false", "--display-wait"]);
+		assert_eq!((args.wrap_sprites, args.display_wait), (Some(false), Some(true)));
+		let mut settings = args.resolve().settings;
+		info.apply(&mut settings);
+		assert!(!settings.quirks.shift_vx);
+		assert_eq!(
+			(settings.quirks.wrap_sprites, settings.quirks.display_wait),
+			(false, true)
+		);
+
+		let mut settings = Args::parse_from(["chip-8", "--start-address", "0x300"])
+			.resolve()
+			.settings;
+		fixture_db().lookup(&CLEAR).unwrap().apply(&mut settings);
+		assert_eq!(settings.memory.start_address, 0x300);
 	}
 }